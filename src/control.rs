@@ -0,0 +1,472 @@
+//! Drone-specific control messages and notifications.
+//! Complements the controller's `DroneCommand` and `DroneEvent` with settings and information that only this drone implementation understands.
+
+use crate::DroneReport;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+use wg_2024::{
+    controller::DroneEvent,
+    network::NodeId,
+    packet::{Packet, PacketType},
+};
+
+/// A control message accepted by the drone on its optional control channel.
+#[derive(Debug, Clone)]
+pub enum DroneControl {
+    /// Turns on per-packet logging for the given session, or turns it off when `None`, the default.
+    /// Per-packet messages of other sessions are only logged while logging is boosted.
+    SetTraceSession(Option<u64>),
+    /// Enables or disables logging of every fragment drop decision. High volume, meant for debugging.
+    SetDropDecisionLogging(bool),
+    /// Sets the PDR to a named preset, see [`PdrPreset`].
+    ApplyPdrPreset(PdrPreset),
+    /// Sends the packet unchanged to the given neighbor, bypassing source routing.
+    ForwardTo(NodeId, Packet),
+    /// Caps the flood path trace length at which the drone responds instead of forwarding,
+    /// or removes the cap when `None`.
+    SetMaxFloodDepth(Option<usize>),
+    /// Limits the number of neighbors a flood request is forwarded to, or removes the cap when `None`.
+    ///
+    /// Without this message, a drone caps its fan-out at 16 and logs an error while it has more
+    /// than 64 neighbors, checked when built and after each `AddSender` and `RemoveSender`. That
+    /// automatic cap is not carried by [`DroneConfig`](crate::DroneConfig). Any explicit choice,
+    /// `None` included, disables it.
+    SetFloodFanOut(Option<usize>),
+    /// Chooses which neighbors receive a flood request when the fan-out cap applies.
+    SetFloodFanOutPolicy(FloodFanOutPolicy),
+    /// Makes the drone answer fragments routed to the given neighbor with `ErrorInRouting`,
+    /// while keeping its channel, so floods still traverse the link.
+    BlackholeNeighbor(NodeId),
+    /// Lifts a blackhole set with [`DroneControl::BlackholeNeighbor`].
+    RestoreNeighbor(NodeId),
+    /// Lifts every blackhole set with [`DroneControl::BlackholeNeighbor`].
+    RestoreAllNeighbors,
+    /// Uses the flood ID as session ID of the flood responses built by the drone instead of a
+    /// random value, so handling floods does not consume the random generator used for drops.
+    /// Shorthand for [`DroneControl::SetFloodSessionStrategy`] with
+    /// [`FloodSessionStrategy::FloodId`] or [`FloodSessionStrategy::Random`].
+    SetDeterministicFloodSession(bool),
+    /// Chooses how the session ID of the flood responses built by the drone is picked.
+    SetFloodSessionStrategy(FloodSessionStrategy),
+    /// Switches the event loop between biased selection (commands, then control messages, then
+    /// packets) and handling messages in the order the drone received them, whatever their channel.
+    ///
+    /// Messages that became ready while the drone was busy are interleaved across the channels,
+    /// since their relative order is unknown.
+    SetFairOrdering(bool),
+    /// Sets the drop rate applied to routed packets of the given kind, or stops dropping them when `None`.
+    ///
+    /// The fragment rate is the drone's PDR, so `None` resets it to `0.0`.
+    SetDropRate(PacketKind, Option<f32>),
+    /// Experimental: lets the drone replace the next hop of a forwarded fragment with one of the
+    /// given neighbors, picked at random in proportion to its weight. The group should usually
+    /// include the original next hop itself. An empty group removes it.
+    #[cfg(feature = "multipath")]
+    SetMultipathGroup(NodeId, Vec<(NodeId, u32)>),
+    /// Sets how long after a crash a replacement built with [`Drone::clone_config`](crate::Drone::clone_config)
+    /// rejects controller commands, modelling a restart delay. Zero by default. `Crash` is always
+    /// accepted, so a controller can still take the replacement down.
+    SetRestartCooldown(Duration),
+    /// Ignores flood requests arriving more than the given duration after the first request of the
+    /// same flood was seen, or accepts them regardless of age when `None`.
+    SetFloodValidity(Option<Duration>),
+    /// Forwards the fragments of each session in ascending index order, holding back up to the
+    /// given number of early fragments per session and NACKing the excess. `None` turns this off
+    /// and releases every held-back fragment. Sessions are forgotten once their last fragment has
+    /// passed, and are otherwise tracked under the [`SessionRetention`] policy; evicting a session
+    /// releases its held-back fragments.
+    SetFragmentOrdering(Option<usize>),
+    /// Chooses how a fragment whose final hop is the drone is handled.
+    SetDestinationIsDronePolicy(DestinationIsDronePolicy),
+    /// Chooses the order in which queued packets are handled when the drone crashes.
+    SetCrashDrainOrder(CrashDrainOrder),
+    /// Chooses how fragments disagreeing with their session's first-seen `total_n_fragments` are handled.
+    SetFragmentTotalCheck(FragmentTotalCheck),
+    /// Turns on verbose logging for the given duration: every drop decision and the per-packet
+    /// messages of every session are logged. Both revert on their own once the duration elapses.
+    BoostLogging(Duration),
+    /// Makes fragment drop decisions count towards [`DroneReport::would_drop`](crate::DroneReport::would_drop)
+    /// instead of dropping, so the drop model can be calibrated on a lossless run.
+    SetMeasureOnly(bool),
+    /// Holds the flood responses built by the drone for the given window after the first one,
+    /// then sends them together, coalescing identical responses. `None` sends them immediately and
+    /// flushes any held response. A window too long to represent holds responses until batching
+    /// is turned off or the drone exits.
+    SetFloodResponseBatching(Option<Duration>),
+    /// Caps the number of floods whose first-seen time is tracked for
+    /// [`DroneControl::SetFloodValidity`], evicting the oldest beyond the cap, or removes the cap
+    /// when `None`. Evicted floods are still forwarded; a late request for one counts as new.
+    SetMaxTrackedFloods(Option<usize>),
+    /// Limits the events sent by the drone to the given number per second, or removes the limit
+    /// when `None`. Beyond the limit, events are dropped, except controller shortcuts which carry
+    /// packets. Events emitted by the common `NetworkNode` handling are not limited.
+    SetEventRateLimit(Option<u32>),
+    /// Makes the drone log its report and send it as a [`DroneNotification::Metrics`].
+    ReportMetrics,
+    /// Models a half-failed node that still receives packets but cannot transmit: while enabled,
+    /// every packet is consumed without sending anything, and fragments are counted as dropped.
+    /// Unlike a crash, the drone stays in its event loop and keeps handling commands.
+    SetTransmitFailure(bool),
+    /// Forwards only the first flood response of each responder to each flood, identified by its
+    /// `flood_id` and initiator, remembering up to the given number of responses, or forwards every
+    /// response when `None`.
+    SetFloodResponseDedup(Option<usize>),
+    /// Enables or disables the [`DroneNotification::Leaving`] topology change signal sent when the
+    /// drone crashes. Enabled by default.
+    SetAnnounceCrash(bool),
+    /// Suspends the data plane: the drone stops taking packets from its channel, which queue up,
+    /// while commands and control messages are still handled. A crash while paused drains the
+    /// queued packets as usual.
+    Pause,
+    /// Resumes taking packets after a [`DroneControl::Pause`].
+    Resume,
+    /// Records when each flood was first seen, for [`Drone::seen_flood_times`](crate::Drone::seen_flood_times).
+    /// Off by default since it keeps an entry per flood; floods are always timed while
+    /// [`DroneControl::SetFloodValidity`] is set.
+    SetFloodTimestamps(bool),
+    /// Chooses how a routed packet arriving with `hop_index` 0, which points at its source, is handled.
+    SetHopIndexZeroPolicy(HopIndexZeroPolicy),
+    /// Enables or disables the events of the given kind sent by the drone. Every kind is enabled by
+    /// default. Controller shortcuts carry packets, so they cannot be disabled. Events emitted by
+    /// the common `NetworkNode` handling are not affected.
+    SetEventEnabled(EventKind, bool),
+    /// Enables or disables recording the last time a packet was forwarded to each neighbor, see
+    /// [`Drone::neighbor_last_activity`](crate::Drone::neighbor_last_activity). Off by default;
+    /// disabling it forgets the recorded times.
+    SetNeighborActivityTracking(bool),
+    /// Chooses how a non-fragment packet whose route ends at the drone is handled when it cannot
+    /// be shortcut to the controller.
+    SetShortcutFailurePolicy(ShortcutFailurePolicy),
+    /// Chooses how long per-session state is kept: the totals of the fragment total check, the
+    /// progress behind session completion events, the last routes of route change detection and
+    /// the reorder buffers of in-order forwarding.
+    SetSessionRetention(SessionRetention),
+    /// Enables or disables sending a [`DroneNotification::NackCopy`] for every NACK the drone
+    /// originates or forwards. High volume, meant for loss analysis.
+    SetNackMirroring(bool),
+    /// Sets how fragments still queued when the drone crashes are handled.
+    SetCrashDrainMode(CrashDrainMode),
+    /// Sets the range of node IDs reserved for drones, or `None` to disable the check.
+    /// The drone warns when it forwards a fragment whose destination falls in the range, since
+    /// fragments never terminate at a drone. By convention drones use IDs 11 to 20.
+    SetDroneIdRange(Option<RangeInclusive<NodeId>>),
+    /// Sets whether a crashing drone still handles the flood requests it drains. Enabled by
+    /// default, which keeps the drone in discovered topologies until it is gone; disabling it makes
+    /// clients stop routing through the drone as soon as it starts crashing.
+    SetAnswerFloodsWhileCrashing(bool),
+    /// Caps the total number of entries kept in the drone's bounded state, split evenly between
+    /// tracked flood times, seen floods, remembered flood responses and each of the four
+    /// per-session maps (fragment totals, completion progress, routes and in-order forwarding).
+    /// Forgotten floods count as new if their requests arrive again. Each share is at
+    /// least one entry, and smaller caps already set are kept. The per-session share replaces the
+    /// [`SessionRetention`] policy with [`SessionRetention::MaxSessions`]. Per-neighbor state is
+    /// bounded by the neighbor count and not part of the budget.
+    SetStateBudget(usize),
+    /// Sets whether the drone refuses to build a NACK for a misrouted packet that is not a
+    /// fragment, instead of NACKing fragment index 0. Either way the attempt is logged, as it
+    /// points to a bug.
+    SetRefuseNonFragmentNacks(bool),
+    /// Chooses how flood requests whose `initiator_id` differs from the first node of their path
+    /// trace are handled. Such requests are malformed or spoofed. Logged by default.
+    SetFloodInitiatorCheck(FloodInitiatorCheck),
+    /// Retries forwarding a fragment to a full neighbor channel up to the given number of times,
+    /// waiting the given delay before the first retry and doubling it before each next one, then
+    /// NACKs the fragment as unreachable. Each delay is capped at 100 ms, and retries stop early
+    /// once their delays would add up to more than 1 s, which bounds how long the drone stalls on
+    /// one fragment. With zero retries, the default, the send waits for room in the channel instead.
+    SetForwardRetry(u32, Duration),
+    /// Designates a packet by `(session_id, fragment_index)` whose handling is traced step by step,
+    /// or clears the designation when `None`. Each time the packet is handled, the trace is logged
+    /// as one block and sent as a [`DroneNotification::DecisionTrace`].
+    TracePacket(Option<(u64, u64)>),
+    /// Reserves a session ID for echo probes, or disables echoing when `None`. A routed packet with
+    /// this session ID is sent straight back to its source along its reversed route instead of
+    /// being forwarded, so a controller can probe individual links.
+    SetEchoSession(Option<u64>),
+    /// Enables or disables [`DroneNotification::SessionCompleted`] when the drone forwards the last
+    /// fragment of a session. The fragments of each session are tracked under the
+    /// [`SessionRetention`] policy.
+    SetSessionCompletionEvents(bool),
+    /// Chooses how routed packets whose route visits the same node twice in a row are handled.
+    SetDuplicateHopPolicy(DuplicateHopPolicy),
+    /// Sets a floor under the PDR, modelling irreducible link loss: fragments are dropped at the
+    /// higher of the PDR and the floor. Zero by default; values outside `[0.0, 1.0]` are rejected.
+    SetPdrFloor(f32),
+    /// Enables or disables logging fragments whose route differs from the route of the previous
+    /// fragment of their session, a sign of route churn upstream. The route of each session is
+    /// tracked under the [`SessionRetention`] policy.
+    SetRouteChangeDetection(bool),
+}
+
+/// Retention of per-session state, which otherwise grows with every session seen over a run.
+///
+/// A session whose state was evicted is treated as new the next time one of its fragments arrives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionRetention {
+    /// Sessions are kept for the whole run.
+    Unbounded,
+    /// Sessions without a fragment for the given duration are evicted. Idle sessions are swept
+    /// at most once per half that duration, so one may outlive it by up to half again.
+    IdleFor(Duration),
+    /// At most the given number of sessions are kept, evicting the least recently used.
+    MaxSessions(usize),
+}
+
+/// Handling of a packet that could not be handed to the controller through a `ControllerShortcut` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortcutFailurePolicy {
+    /// The packet is logged and discarded.
+    Drop,
+    /// The packet is sent back along the reverse of the route it took, if the previous hop is
+    /// still a neighbor, and discarded otherwise.
+    ReturnToSender,
+}
+
+/// Handling of a routed packet whose `hop_index` is 0.
+///
+/// Index 0 of a route is the source, so such a packet is malformed or misrouted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HopIndexZeroPolicy {
+    /// The packet is logged and discarded without a NACK.
+    Drop,
+    /// The packet is logged and handed to the controller through a `ControllerShortcut` event.
+    ControllerShortcut,
+}
+
+/// Order in which the packets still queued at crash time are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashDrainOrder {
+    /// Packets are handled in the order they were received.
+    Fifo,
+    /// Acks, NACKs and flood packets are handled first, so they still get out before the queued
+    /// fragments are NACKed. Each group keeps its receive order.
+    ControlFirst,
+}
+
+/// Handling of the fragments still queued when the drone crashes.
+///
+/// Every queued packet arrived before the crash command was handled, so this decides whether
+/// they are treated as sent to a working drone or to a crashed one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashDrainMode {
+    /// Queued fragments are answered with an `ErrorInRouting` NACK. This is the default.
+    Nack,
+    /// Queued fragments are forwarded normally before the drone exits; only the drop rate applies.
+    Forward,
+}
+
+/// Handling of a fragment whose route ends at the drone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestinationIsDronePolicy {
+    /// Answers with a `DestinationIsDrone` NACK, as the protocol requires.
+    Nack,
+    /// Hands the fragment to the controller through a `ControllerShortcut` event, for topologies
+    /// where a drone temporarily stands in for a server.
+    ControllerShortcut,
+}
+
+/// Handling of fragments whose `total_n_fragments` differs from the first value seen for their session.
+///
+/// A mismatch points at a buggy or malicious sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentTotalCheck {
+    /// Fragments are not checked.
+    Off,
+    /// Mismatching fragments are logged and forwarded as usual.
+    Log,
+    /// Mismatching fragments are logged and discarded without a NACK.
+    Drop,
+}
+
+/// Session ID of the flood responses built by the drone, see
+/// [`DroneControl::SetFloodSessionStrategy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloodSessionStrategy {
+    /// A random value drawn from the drone's random generator. This is the default.
+    Random,
+    /// The flood ID.
+    FloodId,
+    /// A hash of the drone ID and the flood ID, stable across runs and distinct between drones.
+    Hash,
+}
+
+/// Handling of a routed packet whose route repeats a node in consecutive hops, e.g.
+/// `[1, 11, 11, 21]`, see [`DroneControl::SetDuplicateHopPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateHopPolicy {
+    /// Routes are not checked. This is the default.
+    Allow,
+    /// The packet is logged and discarded; fragments are answered with an
+    /// `ErrorInRouting` NACK naming the repeated node.
+    Nack,
+    /// The packet is logged and discarded without a NACK.
+    Drop,
+}
+
+/// Handling of a flood request whose initiator disagrees with its path trace, see
+/// [`DroneControl::SetFloodInitiatorCheck`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloodInitiatorCheck {
+    /// Flood requests are not checked.
+    Off,
+    /// Mismatching flood requests are logged and handled as usual.
+    Log,
+    /// Mismatching flood requests are logged and discarded.
+    Drop,
+}
+
+/// Selection policy for the neighbors receiving a flood request under a fan-out cap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloodFanOutPolicy {
+    /// Picks the neighbors with the lowest IDs, deterministically.
+    LowestIdFirst,
+    /// Picks a random subset of the neighbors using the drone's random generator.
+    Random,
+    /// Rotates the starting neighbor on every flood round.
+    RoundRobin,
+}
+
+/// The kind of a packet, i.e. its [`PacketType`] without the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacketKind {
+    Fragment,
+    Ack,
+    Nack,
+    FloodRequest,
+    FloodResponse,
+}
+
+impl From<&PacketType> for PacketKind {
+    fn from(pack_type: &PacketType) -> Self {
+        match pack_type {
+            PacketType::MsgFragment(_) => PacketKind::Fragment,
+            PacketType::Ack(_) => PacketKind::Ack,
+            PacketType::Nack(_) => PacketKind::Nack,
+            PacketType::FloodRequest(_) => PacketKind::FloodRequest,
+            PacketType::FloodResponse(_) => PacketKind::FloodResponse,
+        }
+    }
+}
+
+/// The kind of a controller event, i.e. its `DroneEvent` variant without the packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    PacketSent,
+    PacketDropped,
+    ControllerShortcut,
+}
+
+impl From<&DroneEvent> for EventKind {
+    fn from(event: &DroneEvent) -> Self {
+        match event {
+            DroneEvent::PacketSent(_) => EventKind::PacketSent,
+            DroneEvent::PacketDropped(_) => EventKind::PacketDropped,
+            DroneEvent::ControllerShortcut(_) => EventKind::ControllerShortcut,
+        }
+    }
+}
+
+/// A drone-specific notification, sent on the optional notification channel.
+///
+/// Complements the controller's `DroneEvent` with information that event type cannot carry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DroneNotification {
+    /// The packet drop rate was changed to the contained value.
+    PdrChanged(f32),
+    /// Periodic liveness signal, see [`Drone::with_heartbeat`](crate::Drone::with_heartbeat).
+    Heartbeat,
+    /// A packet was dropped, sent alongside the controller's `PacketDropped` event when there is one.
+    ///
+    /// `fragment_index` is `0` for flood requests and responses.
+    PacketDropped {
+        session_id: u64,
+        fragment_index: u64,
+        reason: DropReason,
+    },
+    /// The drone's report, sent in reply to [`DroneControl::ReportMetrics`].
+    Metrics(DroneReport),
+    /// The drone started crashing and is leaving the topology, so routes through it are about to
+    /// break. Carries the neighbors it had, e.g. to trigger a new flood from their side.
+    Leaving { neighbors: Vec<NodeId> },
+    /// A copy of a NACK sent by the drone, see [`DroneControl::SetNackMirroring`]. `originated`
+    /// tells NACKs built by the drone apart from NACKs passing through it.
+    NackCopy { nack: Packet, originated: bool },
+    /// The decisions taken while handling the packet designated with [`DroneControl::TracePacket`],
+    /// in order.
+    DecisionTrace {
+        session_id: u64,
+        fragment_index: u64,
+        steps: Vec<String>,
+    },
+    /// The drone forwarded the last fragment of a session, see
+    /// [`DroneControl::SetSessionCompletionEvents`]. `forwarded` counts the distinct fragments of
+    /// the session it forwarded, out of `total`.
+    SessionCompleted {
+        session_id: u64,
+        forwarded: u64,
+        total: u64,
+    },
+}
+
+/// Why the drone dropped a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// A fragment lost to the packet drop rate.
+    Pdr,
+    /// A packet lost to the drop rate of its kind, see [`DroneControl::SetDropRate`].
+    DropRate,
+    /// A fragment discarded by [`FragmentTotalCheck::Drop`].
+    FragmentTotalMismatch,
+    /// A fragment received during the warm-up period, see [`Drone::with_warm_up`](crate::Drone::with_warm_up).
+    WarmUp,
+    /// A fragment that did not fit in its session's reorder buffer, see
+    /// [`DroneControl::SetFragmentOrdering`].
+    ReorderBufferFull,
+    /// A packet whose routing header exceeds the maximum route length.
+    RouteTooLong,
+    /// A routed packet whose `hop_index` is past the end of its route.
+    RouteOverrun,
+    /// A fragment consumed while transmitting is disabled, see [`DroneControl::SetTransmitFailure`].
+    TransmitFailure,
+    /// A routed packet arriving with `hop_index` 0, see [`HopIndexZeroPolicy::Drop`].
+    HopIndexZero,
+    /// A flood request discarded by [`FloodInitiatorCheck::Drop`].
+    FloodInitiatorMismatch,
+    /// A routed packet whose route repeats a node in consecutive hops, see [`DuplicateHopPolicy`].
+    DuplicateHop,
+}
+
+/// Named packet drop rates for typical links.
+///
+/// Presets only set the PDR: the drone models loss, not delay or jitter, so links that differ
+/// only in latency map to the same preset. Each preset applies:
+///
+/// | Preset      | PDR  |
+/// |-------------|------|
+/// | `Clean`     | 0.00 |
+/// | `Wifi`      | 0.05 |
+/// | `Satellite` | 0.10 |
+/// | `Congested` | 0.20 |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdrPreset {
+    /// A lossless link.
+    Clean,
+    /// A typical wireless link with occasional loss.
+    Wifi,
+    /// A long-distance link with moderate loss.
+    Satellite,
+    /// A saturated link losing a significant share of fragments.
+    Congested,
+}
+
+impl PdrPreset {
+    /// Returns the packet drop rate applied by this preset.
+    pub fn pdr(self) -> f32 {
+        match self {
+            PdrPreset::Clean => 0.0,
+            PdrPreset::Wifi => 0.05,
+            PdrPreset::Satellite => 0.1,
+            PdrPreset::Congested => 0.2,
+        }
+    }
+}
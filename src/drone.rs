@@ -1,29 +1,235 @@
 //! Drone implementation module.
 //! Handles packet routing, flooding, and network management for drone nodes.
 
-use crossbeam_channel::{select_biased, Receiver, Sender};
-use network_node::{log_error, log_status, Command, NetworkNode};
+/// Logs a status message for the drone, keeping a copy in its recent log buffer if enabled.
+macro_rules! drone_status {
+    ($drone:expr, $($arg:tt)*) => {{
+        network_node::log_status!($drone.id, $($arg)*);
+        $drone.record_log(format_args!($($arg)*));
+    }};
+}
+
+/// Logs an error message for the drone, keeping a copy in its recent log buffer if enabled.
+macro_rules! drone_error {
+    ($drone:expr, $($arg:tt)*) => {{
+        network_node::log_error!($drone.id, $($arg)*);
+        $drone.record_log(format_args!($($arg)*));
+    }};
+}
+
+mod arrivals;
+mod config;
+mod correlation;
+mod decision_trace;
+mod flood;
+mod logs;
+#[cfg(feature = "multipath")]
+mod multipath;
+mod ordering;
+mod rate_limit;
+mod report;
+mod route;
+mod throughput;
+
+use arrivals::Arrival;
+pub use config::DroneConfig;
+use config::Settings;
+pub use correlation::CorrelatedEvent;
+use decision_trace::packet_fragment_index;
+use flood::flood_key;
+use logs::RecentLogs;
+use ordering::SessionOrder;
+use rate_limit::TokenBucket;
+use report::Stats;
+pub use report::{
+    DroneReport, DroneStatus, ExitReason, NackCounts, PacketCounts, METRICS_CSV_HEADER,
+};
+pub use route::RouteError;
+use route::{destination_in_drone_range, next_hop, repeated_hop, route_too_long};
+use throughput::Throughput;
+
+use crate::control::{
+    CrashDrainMode, CrashDrainOrder, DestinationIsDronePolicy, DroneControl, DroneNotification,
+    DropReason, DuplicateHopPolicy, FloodSessionStrategy, FragmentTotalCheck, HopIndexZeroPolicy,
+    PacketKind, PdrPreset, SessionRetention, ShortcutFailurePolicy,
+};
+use crossbeam_channel::{at, never, select_biased, tick, Receiver, Sender, TrySendError};
+use network_node::{log_error, Command, NetworkNode};
 use rand::{rngs::StdRng, Rng, SeedableRng};
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
 use wg_2024::{
     controller::{DroneCommand, DroneEvent},
     network::{NodeId, SourceRoutingHeader},
     packet::{Nack, NackType, NodeType, Packet, PacketType},
 };
 
+/// Maximum number of hops accepted in a routing header.
+/// Node IDs are `u8`, so no loop-free route can be longer.
+const MAX_ROUTE_LENGTH: usize = 256;
+
+/// Neighbor count above which a drone is considered misconfigured.
+const LARGE_NEIGHBOR_COUNT: usize = 64;
+
+/// Flood fan-out cap applied automatically to drones with more than [`LARGE_NEIGHBOR_COUNT`] neighbors.
+const LARGE_NEIGHBOR_FLOOD_FAN_OUT: usize = 16;
+
+/// Longest delay slept before a single forwarding retry, see [`DroneControl::SetForwardRetry`].
+const MAX_FORWARD_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest total delay slept while retrying to forward one packet.
+const MAX_FORWARD_RETRY_WAIT: Duration = Duration::from_secs(1);
+
+/// Number of structures sharing the budget set with [`DroneControl::SetStateBudget`]: tracked
+/// flood times, seen floods, remembered flood responses and the four per-session maps.
+const BUDGETED_STRUCTURES: usize = 7;
+
+/// Hook run on every NACK originated by the drone; returning `false` suppresses the NACK.
+type NackHook = Box<dyn Fn(&mut Packet) -> bool + Send>;
+
+/// Callback run once when the event loop ends.
+type ExitCallback = Box<dyn FnOnce(ExitReason) + Send>;
+
 /// Implementation of a drone node in the network.
 /// Responsible for routing packets and managing network connections.
 pub struct Drone {
     id: NodeId,
     sim_contr_send: Sender<DroneEvent>,
+    event_subscribers: Vec<Sender<DroneEvent>>,
+    correlated_subscribers: Vec<Sender<CorrelatedEvent>>,
     sim_contr_recv: Receiver<DroneCommand>,
+    control_recv: Receiver<DroneControl>,
+    notification_send: Option<Sender<DroneNotification>>,
+    dead_letter_send: Option<Sender<Packet>>,
     packet_recv: Receiver<Packet>,
     packet_send: HashMap<NodeId, Sender<Packet>>,
     pdr: f32,
+    configured_pdr: f32,
     seen_flood_ids: HashSet<String>,
+    /// Keys of the seen flood set in the order they were added, for evicting the oldest.
+    seen_flood_order: VecDeque<String>,
     random_generator: StdRng,
     crashing_behavior: bool,
     should_exit: bool,
+    settings: Settings,
+    flood_rounds: usize,
+    generated_nacks: Option<Vec<(NackType, SourceRoutingHeader)>>,
+    nack_hook: Option<NackHook>,
+    on_exit: Option<ExitCallback>,
+    stats: Stats,
+    exit_reason: Option<ExitReason>,
+    blackholed_neighbors: HashSet<NodeId>,
+    transmit_failure: bool,
+    paused: bool,
+    /// First-seen `total_n_fragments` and last fragment time of each session.
+    session_fragment_totals: HashMap<u64, (u64, Instant)>,
+    /// Fragments forwarded and last fragment time of each session, for completion events.
+    session_progress: HashMap<u64, (HashSet<u64>, Instant)>,
+    /// Last route and fragment time of each session, for route change detection.
+    session_routes: HashMap<u64, (Vec<NodeId>, Instant)>,
+    throughput: Throughput,
+    started_at: Option<Instant>,
+    /// Messages received but not yet handled in fair ordering mode, oldest first.
+    arrivals: VecDeque<Arrival>,
+    max_lifetime: Option<Duration>,
+    deadline: Receiver<Instant>,
+    heartbeat_interval: Option<Duration>,
+    heartbeat: Receiver<Instant>,
+    recent_logs: Option<RefCell<RecentLogs>>,
+    decision_trace: RefCell<Option<Vec<String>>>,
+    crashed_at: Option<Instant>,
+    /// Crash time of the drone this one replaces, while its restart cooldown may still be running.
+    previous_crash_at: Option<Instant>,
+    flood_first_seen: HashMap<String, Instant>,
+    /// Warm-up fragments set with [`Drone::with_warm_up`], before any were forwarded.
+    warm_up: usize,
+    warm_up_remaining: usize,
+    session_order: HashMap<u64, (SessionOrder, Instant)>,
+    /// Time of the last full sweep of the per-session state under [`SessionRetention::IdleFor`].
+    last_session_sweep: Instant,
+    log_boosted: bool,
+    /// End of the verbose logging window, or `None` while boosted for good.
+    log_boost_until: Option<Instant>,
+    log_boost: Receiver<Instant>,
+    pending_flood_responses: Vec<Packet>,
+    flood_response_flush: Receiver<Instant>,
+    event_bucket: TokenBucket,
+    /// `(flood_id, initiator, responder)` of the flood responses remembered for deduplication.
+    seen_flood_responses: VecDeque<(u64, NodeId, NodeId)>,
+    last_forwarded: HashMap<NodeId, Instant>,
+}
+
+/// Scrambles a value with the SplitMix64 finalizer, for IDs derived from packet or flood fields.
+///
+/// Unlike the standard library hashers, the result is fixed across platforms and releases.
+fn mix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Removes the entries of a per-session map that fall outside the retention policy, based on the
+/// last activity time stored alongside each entry.
+fn retain_sessions<T>(sessions: &mut HashMap<u64, (T, Instant)>, retention: SessionRetention) {
+    match retention {
+        SessionRetention::Unbounded => {}
+        SessionRetention::IdleFor(idle) => {
+            sessions.retain(|_, (_, last_seen)| last_seen.elapsed() <= idle)
+        }
+        SessionRetention::MaxSessions(max_sessions) => {
+            while sessions.len() > max_sessions {
+                let Some(oldest) = sessions
+                    .iter()
+                    .min_by_key(|(_, (_, last_seen))| *last_seen)
+                    .map(|(session_id, _)| *session_id)
+                else {
+                    return;
+                };
+                sessions.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Returns the delays slept before each forwarding retry: doubling from `first`, each capped at
+/// [`MAX_FORWARD_RETRY_DELAY`], and stopping before their total exceeds [`MAX_FORWARD_RETRY_WAIT`].
+fn forward_retry_delays(first: Duration, retries: u32) -> impl Iterator<Item = Duration> {
+    let mut total = Duration::ZERO;
+    std::iter::successors(Some(first.min(MAX_FORWARD_RETRY_DELAY)), |delay| {
+        Some(delay.saturating_mul(2).min(MAX_FORWARD_RETRY_DELAY))
+    })
+    .take(retries as usize)
+    .take_while(move |delay| {
+        total = total.saturating_add(*delay);
+        total <= MAX_FORWARD_RETRY_WAIT
+    })
+}
+
+/// Builds the `ErrorInRouting(next_hop_id)` NACK for a fragment that cannot reach its next hop.
+///
+/// The NACK is routed back from the drone's position in the fragment's route, so the fragment
+/// must not have been advanced yet.
+fn unreachable_hop_nack(packet: &Packet, next_hop_id: NodeId, fragment_index: u64) -> Packet {
+    let nack = Nack {
+        fragment_index,
+        nack_type: NackType::ErrorInRouting(next_hop_id),
+    };
+
+    // Create return route from current position to source
+    let mut hops: Vec<NodeId> =
+        packet.routing_header.hops[..=packet.routing_header.hop_index].to_vec();
+    hops.reverse();
+    Packet {
+        pack_type: PacketType::Nack(nack),
+        routing_header: SourceRoutingHeader {
+            hop_index: 1, // Start at 1 since first hop is current node
+            hops,
+        },
+        session_id: packet.session_id,
+    }
 }
 
 impl NetworkNode for Drone {
@@ -67,64 +273,103 @@ impl NetworkNode for Drone {
     /// Returns `true` if the packet was handled (e.g. responded to with a NACK) such that no further
     /// processing is needed, or `false` if the packet should continue being processed.
     fn handle_routed_packet(&mut self, packet: Packet) -> bool {
+        // Index 0 is the source, so the route cannot be trusted to send a NACK back
+        if packet.routing_header.hop_index == 0 {
+            drone_error!(
+                self,
+                "Received packet with hop_index 0, route {:?}",
+                packet.routing_header.hops
+            );
+            match self.settings.hop_index_zero_policy {
+                HopIndexZeroPolicy::Drop => self.record_drop(&packet, DropReason::HopIndexZero),
+                HopIndexZeroPolicy::ControllerShortcut => {
+                    self.send_event(DroneEvent::ControllerShortcut(packet));
+                }
+            }
+            return false;
+        }
+
         if !self.verify_routing(&packet) {
+            self.record_decision(format_args!("Routing invalid"));
             return false;
         }
+        self.record_decision(format_args!(
+            "Routing verified at hop {}",
+            packet.routing_header.hop_index
+        ));
+
+        if !self.check_duplicate_hops(&packet) {
+            return true;
+        }
+
+        if self.settings.echo_session == Some(packet.session_id) {
+            self.echo(packet);
+            return true;
+        }
 
         // Handle final destination
         if packet.routing_header.hop_index + 1 == packet.routing_header.hops.len() {
             if matches!(packet.pack_type, PacketType::MsgFragment(_)) {
-                let nack = self.build_nack(packet, NackType::DestinationIsDrone);
-                self.forward_packet(nack);
+                match self.settings.destination_is_drone_policy {
+                    DestinationIsDronePolicy::Nack => {
+                        self.trace_packet(
+                            &packet,
+                            format_args!("Fragment addressed to drone, sending NACK"),
+                        );
+                        let nack = self.build_nack(packet, NackType::DestinationIsDrone);
+                        self.send_nack(nack);
+                    }
+                    DestinationIsDronePolicy::ControllerShortcut => {
+                        self.trace_packet(
+                            &packet,
+                            format_args!("Fragment addressed to drone, shortcutting to controller"),
+                        );
+                        self.send_event(DroneEvent::ControllerShortcut(packet));
+                    }
+                }
+                return true;
+            } else if let PacketType::FloodResponse(flood_response) = &packet.pack_type {
+                // Flood responses end at the flood initiator, which is never a drone
+                drone_error!(
+                    self,
+                    "Misrouted response to flood {} ends at this drone, shortcutting to controller",
+                    flood_response.flood_id
+                );
+                self.send_event(DroneEvent::ControllerShortcut(packet));
                 return true;
             } else {
-                if let Err(e) = self
-                    .sim_contr_send
-                    .send(DroneEvent::ControllerShortcut(packet.clone()))
-                {
-                    log_error!(self.id, "Failed to send ControllerShortcut event: {:?}", e);
+                // The route has no hop past this drone, so a shortcut packet is never forwarded
+                if !self.send_event(DroneEvent::ControllerShortcut(packet.clone())) {
+                    self.handle_shortcut_failure(packet);
                 }
-                self.forward_packet(packet);
                 return false;
             }
         }
 
         let next_hop_id = packet.routing_header.hops[packet.routing_header.hop_index + 1];
 
-        // Check if next hop is reachable
-        if !self.packet_send.contains_key(&next_hop_id) {
-            if matches!(packet.pack_type, PacketType::MsgFragment(_)) {
-                // When building Nack for unreachable next hop,
-                // we need to use the current packet state for route back
-                let mut nack_packet = packet.clone();
-                let nack = Nack {
-                    fragment_index: match &packet.pack_type {
-                        PacketType::MsgFragment(f) => f.fragment_index,
-                        _ => 0,
-                    },
-                    nack_type: NackType::ErrorInRouting(next_hop_id),
-                };
-                nack_packet.pack_type = PacketType::Nack(nack);
-
-                // Create return route from current position to source
-                let mut hops: Vec<NodeId> =
-                    packet.routing_header.hops[..=packet.routing_header.hop_index].to_vec();
-                hops.reverse();
-                nack_packet.routing_header = SourceRoutingHeader {
-                    hop_index: 1, // Start at 1 since first hop is current node
-                    hops: hops,
-                };
-
-                self.forward_packet(nack_packet);
+        // Check if next hop is reachable; blackholed neighbors are reported as unreachable
+        if !self.can_forward_to_next(&packet) {
+            self.trace_packet(
+                &packet,
+                format_args!("Next hop {} unreachable", next_hop_id),
+            );
+            self.dead_letter(&packet);
+            if let PacketType::MsgFragment(fragment) = &packet.pack_type {
+                let nack_packet =
+                    unreachable_hop_nack(&packet, next_hop_id, fragment.fragment_index);
+                self.send_nack(nack_packet);
             }
             return false;
         }
 
         match packet.pack_type {
             PacketType::MsgFragment(_) => {
-                if self.crashing_behavior {
+                if self.crashing_behavior && self.settings.crash_drain_mode == CrashDrainMode::Nack
+                {
+                    self.trace_packet(&packet, format_args!("Crashing, sending NACK"));
                     let nack = self.build_nack(packet, NackType::ErrorInRouting(self.get_id()));
-                    self.forward_packet(nack);
+                    self.send_nack(nack);
                     return true;
                 } else {
                     self.handle_message_fragment(packet);
@@ -132,10 +377,19 @@ impl NetworkNode for Drone {
                 }
             }
             _ => {
+                if self.should_drop(PacketKind::from(&packet.pack_type)) {
+                    self.trace_packet(&packet, format_args!("Packet dropped"));
+                    self.record_drop(&packet, DropReason::DropRate);
+                    return false;
+                }
+
                 let mut forward_packet = packet.clone();
                 forward_packet.routing_header.hop_index += 1;
 
-                self.forward_packet(forward_packet);
+                if matches!(forward_packet.pack_type, PacketType::Nack(_)) {
+                    self.mirror_nack(&forward_packet, false);
+                }
+                self.forward_routed(forward_packet);
                 false
             }
         }
@@ -143,12 +397,40 @@ impl NetworkNode for Drone {
 
     /// Handles a command received from the simulation controller by executing the corresponding action.
     fn handle_command(&mut self, command: Command) {
+        if self.should_exit {
+            match self.exit_reason {
+                Some(ExitReason::Crashed) => {
+                    drone_status!(self, "Ignoring command received after crash")
+                }
+                Some(ExitReason::LifetimeExpired) => {
+                    drone_status!(self, "Ignoring command received after lifetime expired")
+                }
+                None => drone_status!(self, "Ignoring command received while exiting"),
+            }
+            return;
+        }
+        if let Some(previous_crash_at) = self.previous_crash_at {
+            // A cooldown too long to represent never ends
+            let cooldown_over = previous_crash_at
+                .checked_add(self.settings.restart_cooldown)
+                .is_some_and(|blocked_until| Instant::now() >= blocked_until);
+            if cooldown_over {
+                self.previous_crash_at = None;
+            } else if !matches!(command, Command::Drone(DroneCommand::Crash)) {
+                drone_error!(self, "Rejecting command received during restart cooldown");
+                return;
+            }
+        }
+
         match command {
             Command::Drone(drone_command) => match drone_command {
-                DroneCommand::AddSender(node_id, sender) => self.add_channel(node_id, sender),
+                DroneCommand::AddSender(node_id, sender) => self.add_sender(node_id, sender),
                 DroneCommand::SetPacketDropRate(new_pdr) => self.set_pdr(new_pdr),
                 DroneCommand::Crash => self.crash(),
-                DroneCommand::RemoveSender(node_id) => self.remove_channel(node_id),
+                DroneCommand::RemoveSender(node_id) => {
+                    self.remove_channel(node_id);
+                    self.cap_large_flood_fan_out();
+                }
             },
             _ => panic!("Drone {} received a wrong command type", self.get_id()),
         }
@@ -165,18 +447,15 @@ impl wg_2024::drone::Drone for Drone {
         packet_send: HashMap<NodeId, Sender<Packet>>,
         pdr: f32,
     ) -> Self {
-        Self {
+        Self::new_with_rng(
             id,
-            sim_contr_send: controller_send,
-            sim_contr_recv: controller_recv,
+            controller_send,
+            controller_recv,
             packet_recv,
             packet_send,
             pdr,
-            seen_flood_ids: HashSet::new(),
-            random_generator: StdRng::from_entropy(),
-            should_exit: false,
-            crashing_behavior: false,
-        }
+            StdRng::from_entropy(),
+        )
     }
 
     /// Executes the main event loop for the drone.
@@ -184,147 +463,5618 @@ impl wg_2024::drone::Drone for Drone {
     /// The loop processes commands from the simulation controller and incoming packets until
     /// a termination condition is met.
     fn run(&mut self) {
+        let started_at = Instant::now();
+        self.started_at = Some(started_at);
+        // A lifetime too long to represent never ends
+        self.deadline = self
+            .max_lifetime
+            .and_then(|max_lifetime| started_at.checked_add(max_lifetime))
+            .map_or_else(never, at);
+        self.heartbeat = self.heartbeat_interval.map_or_else(never, tick);
+
         while !self.should_exit {
+            if self.settings.fair_ordering {
+                if self.deadline.try_recv().is_ok() {
+                    self.expire();
+                } else if self.heartbeat.try_recv().is_ok() {
+                    self.notify(DroneNotification::Heartbeat);
+                } else if self.log_boost.try_recv().is_ok() {
+                    self.end_log_boost();
+                } else if self.flood_response_flush.try_recv().is_ok() {
+                    self.flush_flood_responses();
+                } else {
+                    self.handle_next_arrival();
+                }
+                continue;
+            }
+
+            // Arrivals collected before fair ordering was switched off keep their order
+            if let Some(arrival) = self.pop_arrival() {
+                self.handle_arrival(arrival);
+                continue;
+            }
+
+            let packet_recv = self.data_plane();
             select_biased! {
+                recv(self.deadline) -> _ => self.expire(),
+                recv(self.heartbeat) -> _ => self.notify(DroneNotification::Heartbeat),
+                recv(self.log_boost) -> _ => self.end_log_boost(),
+                recv(self.flood_response_flush) -> _ => self.flush_flood_responses(),
                 recv(self.sim_contr_recv) -> command_res => {
                     if let Ok(drone_command) = command_res {
                         let command = Command::Drone(drone_command);
                         self.handle_command(command);
                     }
                 },
-                recv(self.packet_recv) -> packet_res => {
+                recv(self.control_recv) -> control_res => {
+                    match control_res {
+                        Ok(control) => self.handle_control(control),
+                        // The control channel is optional: stop polling it once its sender is gone
+                        Err(_) => self.control_recv = never(),
+                    }
+                },
+                recv(packet_recv) -> packet_res => {
                     if let Ok(packet) = packet_res {
-                        self.should_exit = self.handle_packet(packet, NodeType::Drone);
+                        self.should_exit = self.dispatch_packet(packet);
                     }
                 }
             }
         }
+
+        self.flush_flood_responses();
+
+        // The common packet handling only requests an exit while the drone is crashing
+        let exit_reason = *self.exit_reason.get_or_insert(ExitReason::Crashed);
+        if let Some(on_exit) = self.on_exit.take() {
+            on_exit(exit_reason);
+        }
+    }
+}
+
+impl fmt::Debug for Drone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Drone")
+            .field("id", &self.id)
+            .field("pdr", &self.pdr)
+            .field("neighbors", &self.neighbors())
+            .field("crashing_behavior", &self.crashing_behavior)
+            .field("should_exit", &self.should_exit)
+            .field("inbound_queue_depth", &self.inbound_queue_depth())
+            .finish()
     }
 }
 
 impl Drone {
-    /// Verifies the routing header of a packet to ensure it is addressed to the current node.
+    /// Creates a new instance of a Drone that uses the provided random number generator.
     ///
-    /// If the packet is misrouted, a NACK is generated and forwarded.
-    /// Returns `true` if the routing is valid, or `false` if a correction was needed.
-    fn verify_routing(&mut self, packet: &Packet) -> bool {
-        let index = packet.routing_header.hop_index;
-        if self.id != packet.routing_header.hops[index] {
-            let mut packet = packet.clone();
-            packet.routing_header.hop_index += 1;
-            let nack = self.build_nack(packet, NackType::UnexpectedRecipient(self.id));
-            self.forward_packet(nack);
-            return false;
-        }
-        true
+    /// Useful when the caller needs to know the generator state, e.g. to precompute the
+    /// drop decisions of an identically seeded clone.
+    /// A PDR outside `[0.0, 1.0]` is logged and clamped to that range (`NaN` becomes `0.0`).
+    pub fn new_with_rng(
+        id: NodeId,
+        controller_send: Sender<DroneEvent>,
+        controller_recv: Receiver<DroneCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        pdr: f32,
+        random_generator: StdRng,
+    ) -> Self {
+        // Apply the same bounds as set_pdr, clamping instead of keeping a previous value
+        let configured_pdr = pdr;
+        let pdr = if (0.0..=1.0).contains(&pdr) {
+            pdr
+        } else {
+            log_error!(id, "invalid PDR value: {}, clamping to [0, 1]", pdr);
+            if pdr > 1.0 {
+                1.0
+            } else {
+                0.0
+            }
+        };
+
+        let mut drone = Self {
+            id,
+            sim_contr_send: controller_send,
+            event_subscribers: Vec::new(),
+            correlated_subscribers: Vec::new(),
+            sim_contr_recv: controller_recv,
+            control_recv: never(),
+            notification_send: None,
+            dead_letter_send: None,
+            packet_recv,
+            packet_send,
+            pdr,
+            configured_pdr,
+            seen_flood_ids: HashSet::new(),
+            seen_flood_order: VecDeque::new(),
+            random_generator,
+            should_exit: false,
+            crashing_behavior: false,
+            settings: Settings::default(),
+            flood_rounds: 0,
+            generated_nacks: None,
+            nack_hook: None,
+            on_exit: None,
+            stats: Stats::default(),
+            exit_reason: None,
+            blackholed_neighbors: HashSet::new(),
+            transmit_failure: false,
+            paused: false,
+            session_fragment_totals: HashMap::new(),
+            session_progress: HashMap::new(),
+            session_routes: HashMap::new(),
+            throughput: Throughput::new(),
+            started_at: None,
+            arrivals: VecDeque::new(),
+            max_lifetime: None,
+            deadline: never(),
+            heartbeat_interval: None,
+            heartbeat: never(),
+            recent_logs: None,
+            decision_trace: RefCell::new(None),
+            crashed_at: None,
+            previous_crash_at: None,
+            flood_first_seen: HashMap::new(),
+            warm_up: 0,
+            warm_up_remaining: 0,
+            session_order: HashMap::new(),
+            last_session_sweep: Instant::now(),
+            log_boosted: false,
+            log_boost_until: None,
+            log_boost: never(),
+            pending_flood_responses: Vec::new(),
+            flood_response_flush: never(),
+            event_bucket: TokenBucket::new(),
+            seen_flood_responses: VecDeque::new(),
+            last_forwarded: HashMap::new(),
+        };
+        drone.cap_large_flood_fan_out();
+        drone
     }
 
-    /// Handles a message fragment packet.
+    /// Creates a drone from its entry in the network initialization file.
     ///
-    /// Depending on the packet drop decision (based on PDR), the packet may be dropped (with a NACK sent)
-    /// or forwarded to the next hop by incrementing its routing header.
-    fn handle_message_fragment(&mut self, packet: Packet) {
-        if self.should_drop_packet() {
-            // Send dropped event
-            if let Err(e) = self
-                .sim_contr_send
-                .send(DroneEvent::PacketDropped(packet.clone()))
-            {
-                log_error!(self.id, "Failed to send PacketDropped event: {:?}", e);
+    /// `senders` may hold the packet channels of any nodes; only those listed in
+    /// `config.connected_node_ids` are kept. Neighbors without a channel are logged and skipped.
+    pub fn from_config(
+        config: &wg_2024::config::Drone,
+        controller_send: Sender<DroneEvent>,
+        controller_recv: Receiver<DroneCommand>,
+        packet_recv: Receiver<Packet>,
+        senders: &HashMap<NodeId, Sender<Packet>>,
+    ) -> Self {
+        let mut packet_send = HashMap::new();
+        for neighbor in &config.connected_node_ids {
+            match senders.get(neighbor) {
+                Some(sender) => {
+                    packet_send.insert(*neighbor, sender.clone());
+                }
+                None => log_error!(config.id, "No packet channel for neighbor {}", neighbor),
             }
+        }
 
-            // Build NACK for dropped packet
-            let mut nack_packet = packet.clone();
-            let nack = Nack {
-                fragment_index: match &packet.pack_type {
-                    PacketType::MsgFragment(f) => f.fragment_index,
-                    _ => 0,
-                },
-                nack_type: NackType::Dropped,
-            };
-            nack_packet.pack_type = PacketType::Nack(nack);
-
-            // Create return route from current position to source
-            let mut hops: Vec<NodeId> =
-                packet.routing_header.hops[..=packet.routing_header.hop_index].to_vec();
-            hops.reverse();
-            nack_packet.routing_header = SourceRoutingHeader {
-                hop_index: 1, // Start at 1 since first hop is current node
-                hops: hops,
-            };
+        <Self as wg_2024::drone::Drone>::new(
+            config.id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            config.pdr,
+        )
+    }
 
-            self.forward_packet(nack_packet);
-            return;
-        }
+    /// Attaches a channel carrying drone-specific control messages.
+    ///
+    /// Control messages are processed after controller commands and before packets.
+    pub fn with_control_channel(mut self, control_recv: Receiver<DroneControl>) -> Self {
+        self.control_recv = control_recv;
+        self
+    }
 
-        // Increment hop index for forwarding
-        let mut forward_packet = packet.clone();
-        forward_packet.routing_header.hop_index += 1;
+    /// Installs a hook invoked on every NACK the drone originates, before it is sent.
+    ///
+    /// The hook may modify the NACK in place; returning `false` suppresses it. The NACK is still
+    /// sent along its routing header, so the hook must leave a route whose next hop is a neighbor,
+    /// otherwise it will not be delivered. The hook is not part of [`DroneConfig`].
+    pub fn with_nack_hook(mut self, hook: impl Fn(&mut Packet) -> bool + Send + 'static) -> Self {
+        self.nack_hook = Some(Box::new(hook));
+        self
+    }
 
-        self.forward_packet(forward_packet);
+    /// Makes the drone shut down on its own once `max_lifetime` has elapsed since `run` started.
+    ///
+    /// Packets still queued at that point are handled normally before the drone exits with
+    /// [`ExitReason::LifetimeExpired`]. A lifetime too long to represent never elapses.
+    pub fn with_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = Some(max_lifetime);
+        self
     }
 
-    /// Determines whether the packet should be dropped based on the current packet drop rate (PDR).
+    /// Makes the drone send a [`DroneNotification::Heartbeat`] every `interval` while it runs,
+    /// including when no packets are flowing.
     ///
-    /// Returns `true` if the packet is to be dropped, or `false` otherwise.
-    fn should_drop_packet(&mut self) -> bool {
-        let pdr_scaled = (self.pdr * 100.0) as i32;
-        self.get_random_generator().gen_range(0..=100) < pdr_scaled
+    /// Requires a notification channel, see [`Drone::with_notification_channel`].
+    pub fn with_heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = Some(interval);
+        self
     }
 
-    /// Sets the packet drop rate (PDR) for the drone.
+    /// Drops (with a NACK) the first `fragments` fragments the drone would forward, modelling a
+    /// node that is not ready yet when traffic starts.
+    pub fn with_warm_up(mut self, fragments: usize) -> Self {
+        self.warm_up = fragments;
+        self.warm_up_remaining = fragments;
+        self
+    }
+
+    /// Installs a callback invoked once, just before [`run`](wg_2024::drone::Drone::run) returns.
     ///
-    /// If the provided `new_pdr` is not within the range `[0.0, 1.0]`, an error is logged and the PDR remains unchanged.
-    fn set_pdr(&mut self, new_pdr: f32) {
-        if new_pdr < 0.0 || new_pdr > 1.0 {
-            log_error!(self.id, "invalid PDR value: {}", new_pdr);
-            return;
-        }
-        self.pdr = new_pdr;
+    /// Lets a controller update its bookkeeping or respawn the drone without joining its thread.
+    /// The callback is not part of [`DroneConfig`].
+    pub fn with_on_exit(mut self, on_exit: impl FnOnce(ExitReason) + Send + 'static) -> Self {
+        self.on_exit = Some(Box::new(on_exit));
+        self
     }
 
-    /// Initiates the crash sequence for the drone.
+    /// Attaches a channel on which the drone sends drone-specific notifications.
+    pub fn with_notification_channel(
+        mut self,
+        notification_send: Sender<DroneNotification>,
+    ) -> Self {
+        self.notification_send = Some(notification_send);
+        self
+    }
+
+    /// Attaches a channel receiving a copy of every packet the drone drops or cannot deliver, in
+    /// addition to any NACK or event, so a controller can inspect lost packets after the fact.
+    pub fn with_dead_letter_channel(mut self, dead_letter_send: Sender<Packet>) -> Self {
+        self.dead_letter_send = Some(dead_letter_send);
+        self
+    }
+
+    /// Registers an additional channel receiving a copy of every event sent by the drone itself.
     ///
-    /// This method processes any remaining packets, updates the drone's state to indicate a crash,
-    /// and logs the crash events.
-    fn crash(&mut self) {
-        log_status!(self.id, "Starting crash sequence");
-        self.crashing_behavior = true;
+    /// Subscribers observe events without taking part in the control plane. Events emitted by the
+    /// common `NetworkNode` handling go through the primary controller channel only.
+    pub fn add_event_subscriber(&mut self, subscriber: Sender<DroneEvent>) {
+        self.event_subscribers.push(subscriber);
+    }
 
-        while let Ok(packet) = self.packet_recv.try_recv() {
-            self.handle_packet(packet, NodeType::Drone);
+    /// Sends an event to the controller and to every event subscriber.
+    ///
+    /// Subscribers whose receiving side is gone are removed.
+    /// Events of disabled kinds and low-priority events beyond the event rate limit are dropped.
+    /// Returns `false` if the controller channel is closed.
+    fn send_event(&mut self, event: DroneEvent) -> bool {
+        if !self.event_allowed(&event) {
+            return true;
         }
 
-        self.should_exit = true;
-        log_status!(self.id, "Crashed");
+        let subscriber_count = self.event_subscribers.len();
+        self.event_subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        self.send_correlated(&event);
+        let removed = subscriber_count - self.event_subscribers.len();
+        if removed > 0 {
+            drone_status!(self, "Removed {} disconnected event subscribers", removed);
+        }
+
+        if let Err(e) = self.sim_contr_send.send(event) {
+            drone_error!(self, "Failed to send event: {:?}", e);
+            return false;
+        }
+        true
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use wg_2024::drone::Drone as _;
+    /// Applies the shortcut failure policy to a packet whose route ends at the drone.
+    fn handle_shortcut_failure(&mut self, packet: Packet) {
+        match self.settings.shortcut_failure_policy {
+            ShortcutFailurePolicy::Drop => {
+                self.trace_packet(&packet, format_args!("Shortcut failed, dropping packet"));
+                self.dead_letter(&packet);
+            }
+            ShortcutFailurePolicy::ReturnToSender => {
+                let mut hops: Vec<NodeId> =
+                    packet.routing_header.hops[..=packet.routing_header.hop_index].to_vec();
+                hops.reverse();
+                let previous_hop = hops.get(1).copied();
+                if !previous_hop.is_some_and(|previous_hop| self.is_neighbor(previous_hop)) {
+                    self.trace_packet(
+                        &packet,
+                        format_args!("Shortcut failed and sender unreachable, dropping packet"),
+                    );
+                    self.dead_letter(&packet);
+                    return;
+                }
 
-    use super::*;
+                self.trace_packet(&packet, format_args!("Shortcut failed, returning packet"));
+                let mut returned = packet;
+                returned.routing_header = SourceRoutingHeader { hop_index: 1, hops };
+                self.forward_routed(returned);
+            }
+        }
+    }
 
-    /// Tests the creation of a Drone instance with the expected initial parameters.
-    #[test]
-    fn test_drone_creation() {
-        let (controller_send, _) = crossbeam_channel::unbounded();
-        let (_, controller_recv) = crossbeam_channel::unbounded();
-        let (_, packet_recv) = crossbeam_channel::unbounded();
-        let packet_send = HashMap::new();
+    /// Sends a copy of a lost packet if a dead-letter channel is attached.
+    fn dead_letter(&self, packet: &Packet) {
+        if let Some(dead_letter_send) = &self.dead_letter_send {
+            if let Err(e) = dead_letter_send.send(packet.clone()) {
+                drone_error!(self, "Failed to send dead letter: {:?}", e);
+            }
+        }
+    }
 
-        let drone = Drone::new(
-            1,
-            controller_send,
-            controller_recv,
-            packet_recv,
-            packet_send,
-            0.0,
+    /// Applies the duplicate hop policy to a routed packet.
+    ///
+    /// Returns `false` if the packet was discarded.
+    fn check_duplicate_hops(&mut self, packet: &Packet) -> bool {
+        if self.settings.duplicate_hop_policy == DuplicateHopPolicy::Allow {
+            return true;
+        }
+        let Some(duplicate) = repeated_hop(&packet.routing_header.hops) else {
+            return true;
+        };
+
+        drone_error!(
+            self,
+            "Route {:?} of session {} repeats node {}",
+            packet.routing_header.hops,
+            packet.session_id,
+            duplicate
         );
+        self.record_drop(packet, DropReason::DuplicateHop);
+        if self.settings.duplicate_hop_policy == DuplicateHopPolicy::Nack
+            && matches!(packet.pack_type, PacketType::MsgFragment(_))
+        {
+            let nack = self.build_nack(packet.clone(), NackType::ErrorInRouting(duplicate));
+            self.send_nack(nack);
+        }
+        false
+    }
 
-        assert_eq!(drone.id, 1);
-        assert_eq!(drone.pdr, 0.0);
-        assert!(drone.packet_send.is_empty());
+    /// Sends an echo probe back to its source along the reversed route.
+    fn echo(&mut self, packet: Packet) {
+        let mut hops: Vec<NodeId> =
+            packet.routing_header.hops[..=packet.routing_header.hop_index].to_vec();
+        hops.reverse();
+        let previous_hop = hops.get(1).copied();
+        if !previous_hop.is_some_and(|previous_hop| self.is_neighbor(previous_hop)) {
+            self.trace_packet(
+                &packet,
+                format_args!("Echo sender unreachable, dropping probe"),
+            );
+            self.dead_letter(&packet);
+            return;
+        }
+
+        self.trace_packet(&packet, format_args!("Echoing probe"));
+        let mut echoed = packet;
+        echoed.routing_header = SourceRoutingHeader { hop_index: 1, hops };
+        self.forward_routed(echoed);
+    }
+
+    /// Sends a notification if a notification channel is attached.
+    fn notify(&self, notification: DroneNotification) {
+        if let Some(notification_send) = &self.notification_send {
+            if let Err(e) = notification_send.send(notification) {
+                drone_error!(self, "Failed to send notification: {:?}", e);
+            }
+        }
+    }
+
+    /// Returns the channel the event loop takes packets from: the packet channel, or a channel that
+    /// never delivers while the drone is paused.
+    fn data_plane(&self) -> Receiver<Packet> {
+        if self.paused {
+            never()
+        } else {
+            self.packet_recv.clone()
+        }
+    }
+
+    /// Applies a drone-specific control message.
+    fn handle_control(&mut self, control: DroneControl) {
+        match control {
+            DroneControl::SetTraceSession(session_id) => {
+                match session_id {
+                    Some(session_id) => drone_status!(self, "Tracing session {}", session_id),
+                    None => drone_status!(self, "Session tracing off"),
+                }
+                self.settings.trace_session = session_id;
+            }
+            DroneControl::ApplyPdrPreset(preset) => self.apply_pdr_preset(preset),
+            DroneControl::ForwardTo(neighbor, packet) => {
+                self.forward_to_neighbor(neighbor, packet);
+            }
+            DroneControl::SetMaxFloodDepth(max_depth) => {
+                drone_status!(self, "Max flood depth set to {:?}", max_depth);
+                self.settings.max_flood_depth = max_depth;
+            }
+            DroneControl::SetDropDecisionLogging(enabled) => {
+                self.settings.log_drop_decisions = enabled
+            }
+            DroneControl::SetFloodFanOut(fan_out) => {
+                drone_status!(self, "Flood fan-out cap set to {:?}", fan_out);
+                self.settings.flood_fan_out = fan_out;
+                self.settings.flood_fan_out_configured = true;
+            }
+            DroneControl::SetFloodFanOutPolicy(policy) => {
+                drone_status!(self, "Flood fan-out policy set to {:?}", policy);
+                self.settings.flood_fan_out_policy = policy;
+            }
+            DroneControl::BlackholeNeighbor(neighbor) => {
+                drone_status!(self, "Blackholing neighbor {}", neighbor);
+                self.blackholed_neighbors.insert(neighbor);
+            }
+            DroneControl::RestoreNeighbor(neighbor) => {
+                if self.blackholed_neighbors.remove(&neighbor) {
+                    drone_status!(self, "Restored neighbor {}", neighbor);
+                }
+            }
+            DroneControl::RestoreAllNeighbors => {
+                drone_status!(self, "Restored all blackholed neighbors");
+                self.blackholed_neighbors.clear();
+            }
+            DroneControl::SetDeterministicFloodSession(enabled) => {
+                drone_status!(self, "Deterministic flood session set to {}", enabled);
+                self.settings.flood_session_strategy = if enabled {
+                    FloodSessionStrategy::FloodId
+                } else {
+                    FloodSessionStrategy::Random
+                };
+            }
+            DroneControl::SetFloodSessionStrategy(strategy) => {
+                drone_status!(self, "Flood session strategy set to {:?}", strategy);
+                self.settings.flood_session_strategy = strategy;
+            }
+            DroneControl::SetFairOrdering(enabled) => {
+                drone_status!(self, "Fair ordering set to {}", enabled);
+                self.settings.fair_ordering = enabled;
+            }
+            DroneControl::SetDropRate(kind, rate) => self.set_drop_rate(kind, rate),
+            DroneControl::SetRestartCooldown(cooldown) => {
+                drone_status!(self, "Restart cooldown set to {:?}", cooldown);
+                self.settings.restart_cooldown = cooldown;
+            }
+            DroneControl::SetFloodValidity(validity) => {
+                drone_status!(self, "Flood validity set to {:?}", validity);
+                self.settings.flood_validity = validity;
+            }
+            DroneControl::SetFragmentOrdering(capacity) => {
+                drone_status!(self, "In-order forwarding buffer set to {:?}", capacity);
+                self.settings.fragment_ordering = capacity;
+                if capacity.is_none() {
+                    self.release_held_fragments();
+                }
+            }
+            DroneControl::SetDestinationIsDronePolicy(policy) => {
+                drone_status!(self, "DestinationIsDrone policy set to {:?}", policy);
+                self.settings.destination_is_drone_policy = policy;
+            }
+            DroneControl::SetStateBudget(budget) => self.set_state_budget(budget),
+            DroneControl::SetRouteChangeDetection(enabled) => {
+                drone_status!(self, "Route change detection set to {}", enabled);
+                self.settings.route_change_detection = enabled;
+                if !enabled {
+                    self.session_routes.clear();
+                }
+            }
+            DroneControl::SetPdrFloor(floor) => {
+                if !(0.0..=1.0).contains(&floor) {
+                    drone_error!(self, "invalid PDR floor: {}", floor);
+                } else {
+                    drone_status!(self, "PDR floor set to {}", floor);
+                    self.settings.pdr_floor = floor;
+                }
+            }
+            DroneControl::SetDuplicateHopPolicy(policy) => {
+                drone_status!(self, "Duplicate hop policy set to {:?}", policy);
+                self.settings.duplicate_hop_policy = policy;
+            }
+            DroneControl::SetSessionCompletionEvents(enabled) => {
+                drone_status!(self, "Session completion events set to {}", enabled);
+                self.settings.session_completion = enabled;
+                if !enabled {
+                    self.session_progress.clear();
+                }
+            }
+            DroneControl::SetEchoSession(session_id) => {
+                drone_status!(self, "Echo session set to {:?}", session_id);
+                self.settings.echo_session = session_id;
+            }
+            DroneControl::TracePacket(packet) => {
+                drone_status!(self, "Traced packet set to {:?}", packet);
+                self.settings.traced_packet = packet;
+            }
+            DroneControl::SetForwardRetry(retries, delay) => {
+                drone_status!(
+                    self,
+                    "Forward retry set to {} retries from {:?}",
+                    retries,
+                    delay
+                );
+                self.settings.forward_retries = retries;
+                self.settings.forward_retry_delay = delay;
+            }
+            DroneControl::SetFloodInitiatorCheck(check) => {
+                drone_status!(self, "Flood initiator check set to {:?}", check);
+                self.settings.flood_initiator_check = check;
+            }
+            DroneControl::SetRefuseNonFragmentNacks(enabled) => {
+                drone_status!(self, "Refusing non-fragment NACKs set to {}", enabled);
+                self.settings.refuse_non_fragment_nacks = enabled;
+            }
+            DroneControl::SetAnswerFloodsWhileCrashing(enabled) => {
+                drone_status!(self, "Answering floods while crashing set to {}", enabled);
+                self.settings.answer_floods_while_crashing = enabled;
+            }
+            DroneControl::SetDroneIdRange(range) => {
+                drone_status!(self, "Drone ID range set to {:?}", range);
+                self.settings.drone_id_range = range;
+            }
+            DroneControl::SetCrashDrainMode(mode) => {
+                drone_status!(self, "Crash drain mode set to {:?}", mode);
+                self.settings.crash_drain_mode = mode;
+            }
+            DroneControl::SetCrashDrainOrder(order) => {
+                drone_status!(self, "Crash drain order set to {:?}", order);
+                self.settings.crash_drain_order = order;
+            }
+            #[cfg(feature = "multipath")]
+            DroneControl::SetMultipathGroup(next_hop, weights) => {
+                self.set_multipath_group(next_hop, weights)
+            }
+            DroneControl::SetFragmentTotalCheck(check) => {
+                drone_status!(self, "Fragment total check set to {:?}", check);
+                self.settings.fragment_total_check = check;
+            }
+            DroneControl::BoostLogging(duration) => self.boost_logging(duration),
+            DroneControl::SetMeasureOnly(enabled) => {
+                drone_status!(self, "Measure-only drops set to {}", enabled);
+                self.settings.measure_only = enabled;
+            }
+            DroneControl::SetFloodResponseDedup(capacity) => {
+                drone_status!(self, "Flood response dedup capacity set to {:?}", capacity);
+                self.settings.flood_response_dedup = capacity;
+                let excess = self
+                    .seen_flood_responses
+                    .len()
+                    .saturating_sub(capacity.unwrap_or(0));
+                self.seen_flood_responses.drain(..excess);
+            }
+            DroneControl::SetEventEnabled(kind, enabled) => self.set_event_enabled(kind, enabled),
+            DroneControl::SetNackMirroring(enabled) => {
+                drone_status!(self, "NACK mirroring set to {}", enabled);
+                self.settings.mirror_nacks = enabled;
+            }
+            DroneControl::SetSessionRetention(retention) => {
+                drone_status!(self, "Session retention set to {:?}", retention);
+                self.settings.session_retention = retention;
+                self.sweep_sessions();
+            }
+            DroneControl::SetShortcutFailurePolicy(policy) => {
+                drone_status!(self, "Shortcut failure policy set to {:?}", policy);
+                self.settings.shortcut_failure_policy = policy;
+            }
+            DroneControl::SetNeighborActivityTracking(enabled) => {
+                drone_status!(self, "Neighbor activity tracking set to {}", enabled);
+                self.settings.neighbor_activity = enabled;
+                if !enabled {
+                    self.last_forwarded.clear();
+                }
+            }
+            DroneControl::SetHopIndexZeroPolicy(policy) => {
+                drone_status!(self, "hop_index 0 policy set to {:?}", policy);
+                self.settings.hop_index_zero_policy = policy;
+            }
+            DroneControl::SetFloodTimestamps(enabled) => {
+                drone_status!(self, "Flood timestamps set to {}", enabled);
+                self.settings.flood_timestamps = enabled;
+            }
+            DroneControl::Pause => {
+                drone_status!(self, "Paused");
+                self.paused = true;
+            }
+            DroneControl::Resume => {
+                drone_status!(self, "Resumed");
+                self.paused = false;
+            }
+            DroneControl::SetAnnounceCrash(enabled) => {
+                drone_status!(self, "Crash announcement set to {}", enabled);
+                self.settings.announce_crash = enabled;
+            }
+            DroneControl::SetTransmitFailure(enabled) => {
+                drone_status!(self, "Transmit failure set to {}", enabled);
+                self.transmit_failure = enabled;
+            }
+            DroneControl::ReportMetrics => {
+                let report = self.report();
+                drone_status!(self, "{}", report);
+                self.notify(DroneNotification::Metrics(report));
+            }
+            DroneControl::SetEventRateLimit(rate) => {
+                drone_status!(self, "Event rate limit set to {:?} per second", rate);
+                self.settings.event_rate_limit = rate;
+                if let Some(rate) = rate {
+                    self.event_bucket.fill(rate);
+                }
+            }
+            DroneControl::SetMaxTrackedFloods(max_tracked) => {
+                drone_status!(self, "Max tracked floods set to {:?}", max_tracked);
+                self.settings.max_tracked_floods = max_tracked;
+                self.evict_tracked_floods();
+            }
+            DroneControl::SetFloodResponseBatching(window) => {
+                drone_status!(self, "Flood response batching window set to {:?}", window);
+                self.settings.flood_response_batching = window;
+                if window.is_none() {
+                    self.flush_flood_responses();
+                }
+            }
+        }
+    }
+
+    /// Turns on verbose logging until `duration` has elapsed, see [`DroneControl::BoostLogging`].
+    ///
+    /// A new boost replaces the deadline of the current one. A duration too long to represent
+    /// boosts logging for good.
+    fn boost_logging(&mut self, duration: Duration) {
+        drone_status!(self, "Verbose logging for {:?}", duration);
+        let until = Instant::now().checked_add(duration);
+        self.log_boosted = true;
+        self.log_boost_until = until;
+        self.log_boost = until.map_or_else(never, at);
+    }
+
+    /// Ends the verbose logging window opened by [`DroneControl::BoostLogging`].
+    fn end_log_boost(&mut self) {
+        self.log_boosted = false;
+        self.log_boost_until = None;
+        self.log_boost = never();
+        drone_status!(self, "Verbose logging ended");
+    }
+
+    /// Returns `true` while a verbose logging window is open.
+    ///
+    /// The deadline is checked here too, so logging reverts on time even between loop iterations.
+    fn is_log_boosted(&self) -> bool {
+        self.log_boosted
+            && !self
+                .log_boost_until
+                .is_some_and(|until| Instant::now() >= until)
+    }
+
+    /// Adds a sender towards `node_id`, telling redundant adds apart from replacements in the logs.
+    ///
+    /// Resending the channel already registered for a neighbor is a no-op, which makes
+    /// controllers that retry commands visible without disturbing the topology.
+    fn add_sender(&mut self, node_id: NodeId, sender: Sender<Packet>) {
+        match self.packet_send.get(&node_id) {
+            Some(existing) if existing.same_channel(&sender) => {
+                drone_status!(
+                    self,
+                    "Redundant AddSender for {}, channel unchanged",
+                    node_id
+                );
+            }
+            Some(_) => {
+                drone_status!(self, "Replacing channel towards {}", node_id);
+                self.add_channel(node_id, sender);
+            }
+            None => {
+                self.add_channel(node_id, sender);
+                self.cap_large_flood_fan_out();
+            }
+        }
+    }
+
+    /// Caps the flood fan-out at [`LARGE_NEIGHBOR_FLOOD_FAN_OUT`] while the drone has more than
+    /// [`LARGE_NEIGHBOR_COUNT`] neighbors, and lifts that cap once it no longer does, unless a cap
+    /// was set with [`DroneControl::SetFloodFanOut`].
+    ///
+    /// Flooding to every neighbor of an oversized neighbor set would be very expensive.
+    fn cap_large_flood_fan_out(&mut self) {
+        // Without an explicit choice, any cap in place is the automatic one
+        if self.settings.flood_fan_out_configured {
+            return;
+        }
+        let neighbor_count = self.packet_send.len();
+        match self.settings.flood_fan_out {
+            None if neighbor_count > LARGE_NEIGHBOR_COUNT => {
+                drone_error!(
+                    self,
+                    "{} neighbors (more than {}), capping flood fan-out at {}",
+                    neighbor_count,
+                    LARGE_NEIGHBOR_COUNT,
+                    LARGE_NEIGHBOR_FLOOD_FAN_OUT
+                );
+                self.settings.flood_fan_out = Some(LARGE_NEIGHBOR_FLOOD_FAN_OUT);
+            }
+            Some(_) if neighbor_count <= LARGE_NEIGHBOR_COUNT => {
+                drone_status!(
+                    self,
+                    "{} neighbors, lifting the automatic flood fan-out cap",
+                    neighbor_count
+                );
+                self.settings.flood_fan_out = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets the PDR to a named preset.
+    pub fn apply_pdr_preset(&mut self, preset: PdrPreset) {
+        drone_status!(self, "Applying PDR preset {:?}", preset);
+        self.set_pdr(preset.pdr());
+    }
+
+    /// Logs a per-packet message if the packet belongs to the traced session or logging is boosted.
+    fn trace_packet(&self, packet: &Packet, message: fmt::Arguments) {
+        self.record_decision(message);
+        if self.is_log_boosted() || self.settings.trace_session == Some(packet.session_id) {
+            drone_status!(self, "[session {}] {}", packet.session_id, message);
+        }
+    }
+
+    /// Changes the drone's ID, e.g. to reuse a pooled drone object under a new identity.
+    ///
+    /// Routing headers and flood path traces refer to the drone by ID, so this is only safe before
+    /// [`run`](wg_2024::drone::Drone::run) is called: neighbors and in-flight packets built for the
+    /// old ID would be NACKed as misrouted. Changing the ID of a drone that already ran is logged
+    /// as an error, but still applied.
+    pub fn set_id(&mut self, id: NodeId) {
+        if self.started_at.is_some() {
+            drone_error!(
+                self,
+                "Changing ID to {} after the drone started running",
+                id
+            );
+        }
+        drone_status!(self, "ID changed to {}", id);
+        self.id = id;
+    }
+
+    /// Returns `true` once the drone has started crashing.
+    ///
+    /// Same as `NetworkNode::get_crashing_behavior`, without requiring the trait in scope.
+    pub fn is_crashing(&self) -> bool {
+        self.crashing_behavior
+    }
+
+    /// Returns `true` while the data plane is paused, see [`DroneControl::Pause`].
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns a fingerprint of the random generator's current state, for reproducibility audits.
+    ///
+    /// The value is opaque, but two drones whose generators are in the same state return the same
+    /// value, so comparing fingerprints logged at the same point of two runs shows whether they
+    /// diverged before it. Taking a fingerprint does not advance the generator. The fingerprint is
+    /// also logged when a crash starts.
+    ///
+    /// This is not the serialized state: it is the next value the generator would draw, so it
+    /// cannot restore a generator, and two different states may rarely share a fingerprint. It
+    /// needs no serde support, hence no feature gate.
+    pub fn rng_fingerprint(&self) -> u64 {
+        self.random_generator.clone().gen()
+    }
+
+    /// Returns `true` if the hop following the packet's `hop_index` is a neighbor the drone would
+    /// forward to, without sending or changing anything.
+    ///
+    /// Blackholed neighbors count as unreachable, as when forwarding. Returns `false` if the route
+    /// ends at the current hop.
+    pub fn can_forward_to_next(&self, packet: &Packet) -> bool {
+        next_hop(&packet.routing_header).is_some_and(|next_hop_id| {
+            self.packet_send.contains_key(&next_hop_id)
+                && !self.blackholed_neighbors.contains(&next_hop_id)
+        })
+    }
+
+    /// Returns the PDR last requested for the drone, at construction or by the controller, even if
+    /// it was rejected or clamped. Compare with [`Drone::effective_pdr`] to detect an adjusted value.
+    pub fn configured_pdr(&self) -> f32 {
+        self.configured_pdr
+    }
+
+    /// Returns the number of drops expected among `n` fragments forwarded with the given PDR.
+    ///
+    /// Drops are independent, so the observed count follows a binomial distribution with standard
+    /// deviation `sqrt(n * pdr * (1 - pdr))`. Tests comparing against this value should allow a
+    /// tolerance of a few standard deviations; 4 keeps a seeded test far from the edge.
+    pub fn expected_drops(pdr: f32, n: u64) -> f64 {
+        pdr as f64 * n as f64
+    }
+
+    /// Returns the PDR the drone actually applies to fragments: 1 while warming up, otherwise the
+    /// PDR raised to the PDR floor if one is set.
+    pub fn effective_pdr(&self) -> f32 {
+        if self.warm_up_remaining > 0 {
+            return 1.0;
+        }
+        self.pdr.max(self.settings.pdr_floor)
+    }
+
+    /// Returns the floor set under the PDR with [`DroneControl::SetPdrFloor`], zero if none.
+    pub fn pdr_floor(&self) -> f32 {
+        self.settings.pdr_floor
+    }
+
+    /// Returns the number of packets currently waiting in the inbound packet channel.
+    ///
+    /// This is a snapshot: other nodes may enqueue packets (and the drone may consume them)
+    /// concurrently, so the value can be stale as soon as it is returned.
+    pub fn inbound_queue_depth(&self) -> usize {
+        let arrived = self
+            .arrivals
+            .iter()
+            .filter(|arrival| matches!(arrival, Arrival::Packet(_)))
+            .count();
+        self.packet_recv.len() + arrived
+    }
+
+    /// Returns the IDs of the drone's current neighbors in ascending order.
+    pub fn neighbors(&self) -> Vec<NodeId> {
+        let mut neighbors: Vec<NodeId> = self.packet_send.keys().copied().collect();
+        neighbors.sort();
+        neighbors
+    }
+
+    /// Returns the IDs of the currently blackholed neighbors in ascending order.
+    pub fn blackholed_neighbors(&self) -> Vec<NodeId> {
+        let mut neighbors: Vec<NodeId> = self.blackholed_neighbors.iter().copied().collect();
+        neighbors.sort();
+        neighbors
+    }
+
+    /// Returns `true` if the drone currently has a sender channel towards `node`.
+    pub fn is_neighbor(&self, node: NodeId) -> bool {
+        self.packet_send.contains_key(&node)
+    }
+
+    /// Sends a packet directly to `neighbor`, ignoring its routing header.
+    ///
+    /// The packet is sent exactly as given, so its `hop_index` is not advanced. Intended for
+    /// link-level diagnostics such as controller-injected probes.
+    /// Returns `true` if the packet was handed to the neighbor's channel.
+    pub fn forward_to_neighbor(&mut self, neighbor: NodeId, packet: Packet) -> bool {
+        let Some(sender) = self.packet_send.get(&neighbor) else {
+            drone_error!(self, "Cannot forward to {}: not a neighbor", neighbor);
+            return false;
+        };
+
+        match sender.send(packet) {
+            Ok(()) => {
+                drone_status!(self, "Forwarded packet directly to {}", neighbor);
+                self.record_forward(neighbor);
+                true
+            }
+            Err(e) => {
+                drone_error!(self, "Failed to forward packet to {}: {:?}", neighbor, e);
+                false
+            }
+        }
+    }
+
+    /// Processes a single packet synchronously, outside of the event loop.
+    ///
+    /// Returns `true` if the drone would exit after this packet.
+    pub fn process_packet_once(&mut self, packet: Packet) -> bool {
+        self.dispatch_packet(packet)
+    }
+
+    /// Same as [`Drone::process_packet_once`], also returning the events emitted to the controller
+    /// while handling the packet, in emission order.
+    ///
+    /// The events are still delivered to the controller afterwards, so tests reading its channel
+    /// keep working.
+    pub fn process_packet_capturing_events(&mut self, packet: Packet) -> (bool, Vec<DroneEvent>) {
+        let (capture_send, capture_recv) = crossbeam_channel::unbounded();
+        let controller_send = std::mem::replace(&mut self.sim_contr_send, capture_send);
+        let should_exit = self.dispatch_packet(packet);
+        self.sim_contr_send = controller_send;
+
+        let events: Vec<DroneEvent> = capture_recv.try_iter().collect();
+        for event in &events {
+            if let Err(e) = self.sim_contr_send.send(event.clone()) {
+                drone_error!(self, "Failed to send event: {:?}", e);
+            }
+        }
+        (should_exit, events)
+    }
+
+    /// Applies a single controller command synchronously, outside of the event loop.
+    pub fn apply_command(&mut self, command: DroneCommand) {
+        self.handle_command(Command::Drone(command));
+    }
+
+    /// Starts recording every NACK originated by this drone, discarding any previous recording.
+    ///
+    /// Meant for tests: recorded NACKs can be retrieved with [`Drone::drain_generated_nacks`].
+    pub fn capture_nacks(&mut self) {
+        self.generated_nacks = Some(Vec::new());
+    }
+
+    /// Returns the NACKs originated since capture started or since the last drain.
+    ///
+    /// Returns an empty vector if capture was never started.
+    pub fn drain_generated_nacks(&mut self) -> Vec<(NackType, SourceRoutingHeader)> {
+        self.generated_nacks
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Forwards a NACK originated by this drone, recording it if capture is enabled.
+    ///
+    /// The NACK hook, if any, runs first and may modify or suppress the NACK.
+    fn send_nack(&mut self, mut nack: Packet) {
+        if let Some(nack_hook) = &self.nack_hook {
+            if !nack_hook(&mut nack) {
+                drone_status!(self, "NACK suppressed by hook");
+                return;
+            }
+        }
+
+        if let PacketType::Nack(n) = &nack.pack_type {
+            self.record_decision(format_args!("Sending {:?} NACK", n.nack_type));
+            self.stats.nacks_sent.record(&n.nack_type);
+            if let Some(generated_nacks) = self.generated_nacks.as_mut() {
+                generated_nacks.push((n.nack_type.clone(), nack.routing_header.clone()));
+            }
+        }
+        self.mirror_nack(&nack, true);
+        self.forward_routed(nack);
+    }
+
+    /// Sends a copy of a NACK about to be forwarded, if NACK mirroring is enabled.
+    fn mirror_nack(&self, nack: &Packet, originated: bool) {
+        if self.settings.mirror_nacks {
+            self.notify(DroneNotification::NackCopy {
+                nack: nack.clone(),
+                originated,
+            });
+        }
+    }
+
+    /// Forwards a packet to the node at its `hop_index`, recording the activity towards it.
+    fn forward_routed(&mut self, packet: Packet) {
+        let next_hop_id = packet
+            .routing_header
+            .hops
+            .get(packet.routing_header.hop_index)
+            .copied();
+        if next_hop_id.is_some_and(|next_hop_id| self.refuses_self_send(&packet, next_hop_id)) {
+            return;
+        }
+        self.forward_packet(packet);
+        if let Some(next_hop_id) = next_hop_id {
+            self.record_forward(next_hop_id);
+        }
+    }
+
+    /// Returns `true` if the packet must not be sent because it would loop back into the drone's
+    /// own channel during the crash drain, which would then never run dry on a self-referential route.
+    fn refuses_self_send(&self, packet: &Packet, next_hop_id: NodeId) -> bool {
+        if !self.crashing_behavior || next_hop_id != self.id {
+            return false;
+        }
+        drone_error!(
+            self,
+            "Not sending packet of session {} back to this drone while crashing, route {:?}",
+            packet.session_id,
+            packet.routing_header.hops
+        );
+        self.dead_letter(packet);
+        true
+    }
+
+    /// Records that a packet was just forwarded to `neighbor`, if neighbor activity is tracked.
+    fn record_forward(&mut self, neighbor: NodeId) {
+        if self.settings.neighbor_activity && self.packet_send.contains_key(&neighbor) {
+            self.last_forwarded.insert(neighbor, Instant::now());
+        }
+    }
+
+    /// Returns the last time the drone forwarded a packet to each neighbor, e.g. to flag idle links.
+    ///
+    /// Only packets forwarded by the drone's own handling count; floods forwarded by the common
+    /// `NetworkNode` handling do not. Empty unless
+    /// [`DroneControl::SetNeighborActivityTracking`] is enabled.
+    pub fn neighbor_last_activity(&self) -> HashMap<NodeId, Instant> {
+        self.last_forwarded.clone()
+    }
+
+    /// Processes an incoming packet, tracing its handling if it is the designated packet.
+    ///
+    /// Returns `true` if the drone should exit.
+    fn dispatch_packet(&mut self, packet: Packet) -> bool {
+        if !self.start_decision_trace(&packet) {
+            return self.handle_incoming(packet);
+        }
+        let (session_id, fragment_index) = (packet.session_id, packet_fragment_index(&packet));
+        let should_exit = self.handle_incoming(packet);
+        self.finish_decision_trace(session_id, fragment_index);
+        should_exit
+    }
+
+    /// Handles an incoming packet, applying drone-specific policies before the common handling.
+    ///
+    /// Returns `true` if the drone should exit.
+    fn handle_incoming(&mut self, packet: Packet) -> bool {
+        // Reject oversized routes before any per-hop work
+        if route_too_long(&packet.routing_header.hops) {
+            drone_error!(
+                self,
+                "Dropping packet with {} hops (max {})",
+                packet.routing_header.hops.len(),
+                MAX_ROUTE_LENGTH
+            );
+            self.record_drop(&packet, DropReason::RouteTooLong);
+            return false;
+        }
+
+        self.stats.received.record(&packet.pack_type);
+        self.throughput.record();
+
+        if self.transmit_failure {
+            self.trace_packet(&packet, format_args!("Transmit failure, packet consumed"));
+            if matches!(packet.pack_type, PacketType::MsgFragment(_)) {
+                self.record_drop(&packet, DropReason::TransmitFailure);
+            }
+            return false;
+        }
+
+        if let PacketType::FloodResponse(flood_response) = &packet.pack_type {
+            if self.is_duplicate_flood_response(flood_response) {
+                drone_status!(
+                    self,
+                    "Suppressing duplicate response to flood {}",
+                    flood_response.flood_id
+                );
+                return false;
+            }
+        }
+
+        if let PacketType::FloodRequest(flood_request) = &packet.pack_type {
+            // Drones never initiate floods, so this can only be a forged or misrouted request
+            if flood_request.initiator_id == self.id {
+                drone_status!(
+                    self,
+                    "Ignoring flood {} claiming this drone as initiator",
+                    flood_request.flood_id
+                );
+                return false;
+            }
+
+            if !self.check_flood_initiator(&packet, flood_request) {
+                return false;
+            }
+
+            if self.crashing_behavior && !self.settings.answer_floods_while_crashing {
+                drone_status!(
+                    self,
+                    "Ignoring flood {} while crashing",
+                    flood_request.flood_id
+                );
+                return false;
+            }
+
+            if self.should_drop(PacketKind::FloodRequest) {
+                drone_status!(self, "Flood {} dropped", flood_request.flood_id);
+                self.record_drop(&packet, DropReason::DropRate);
+                return false;
+            }
+
+            let flood_key = flood_key(flood_request.flood_id, flood_request.initiator_id);
+            if self.seen_flood_ids.contains(&flood_key) {
+                self.stats.floods_deduplicated += 1;
+            } else {
+                self.stats.floods_seen += 1;
+                self.remember_seen_flood(flood_key.clone());
+            }
+
+            if self.settings.flood_timestamps && self.settings.flood_validity.is_none() {
+                self.track_flood(flood_key.clone());
+            }
+            if let Some(validity) = self.settings.flood_validity {
+                let first_seen = self.track_flood(flood_key);
+                if first_seen.elapsed() > validity {
+                    drone_status!(
+                        self,
+                        "Ignoring flood {} received {:?} after its first request",
+                        flood_request.flood_id,
+                        first_seen.elapsed()
+                    );
+                    return false;
+                }
+            }
+
+            let flood_request = flood_request.clone();
+            if self.flood_depth_reached(&flood_request) {
+                drone_status!(
+                    self,
+                    "Flood {} reached max depth, sending response",
+                    flood_request.flood_id
+                );
+                // Answering processes the flood, so later requests for it are duplicates
+                self.seen_flood_ids.insert(flood_key(
+                    flood_request.flood_id,
+                    flood_request.initiator_id,
+                ));
+                self.respond_to_flood(flood_request);
+                return false;
+            }
+            // The common handling draws flood response session IDs from the RNG and sends its
+            // responses right away, so deterministic sessions and batching require handling the
+            // flood here
+            if self.settings.flood_fan_out.is_some()
+                || self.settings.flood_session_strategy != FloodSessionStrategy::Random
+                || self.settings.flood_response_batching.is_some()
+            {
+                self.forward_flood_capped(flood_request, packet.routing_header, packet.session_id);
+                return false;
+            }
+        }
+
+        self.handle_packet(packet, NodeType::Drone)
+    }
+
+    /// Returns `true` if the packet's `hop_index` is within its route and points at this drone.
+    ///
+    /// Unlike the checks done while handling a packet, this has no side effects.
+    pub fn is_routing_valid(&self, packet: &Packet) -> bool {
+        packet
+            .routing_header
+            .hops
+            .get(packet.routing_header.hop_index)
+            == Some(&self.id)
+    }
+
+    /// Verifies the routing header of a packet to ensure it is addressed to the current node.
+    ///
+    /// If the packet is misrouted, a NACK is generated and forwarded; a packet whose `hop_index` is
+    /// past the end of its route is dropped.
+    /// Returns `true` if the routing is valid, or `false` if a correction was needed.
+    fn verify_routing(&mut self, packet: &Packet) -> bool {
+        if self.is_routing_valid(packet) {
+            return true;
+        }
+
+        let index = packet.routing_header.hop_index;
+        if index >= packet.routing_header.hops.len() {
+            drone_error!(
+                self,
+                "hop_index {} is past the end of the route, dropping packet",
+                index
+            );
+            self.record_drop(packet, DropReason::RouteOverrun);
+            return false;
+        }
+
+        if self.refuses_nack(packet) {
+            return false;
+        }
+        let mut packet = packet.clone();
+        packet.routing_header.hop_index += 1;
+        let nack = self.build_nack(packet, NackType::UnexpectedRecipient(self.id));
+        self.send_nack(nack);
+        false
+    }
+
+    /// Handles a message fragment packet.
+    ///
+    /// Depending on the packet drop decision (based on PDR), the packet may be dropped (with a NACK sent)
+    /// or forwarded to the next hop by incrementing its routing header. Fragments received during
+    /// the warm-up period are always dropped. With in-order forwarding enabled, fragments may be
+    /// held back until their predecessors in the session have passed.
+    fn handle_message_fragment(&mut self, packet: Packet) {
+        self.detect_route_change(&packet);
+        if !self.check_fragment_total(&packet) {
+            return;
+        }
+
+        if self.warm_up_remaining > 0 {
+            self.warm_up_remaining -= 1;
+            self.trace_packet(&packet, format_args!("Fragment dropped during warm-up"));
+            self.drop_fragment(packet, DropReason::WarmUp);
+            return;
+        }
+
+        if self.should_drop(PacketKind::Fragment) {
+            if self.settings.measure_only {
+                self.stats.would_drop += 1;
+                self.trace_packet(&packet, format_args!("Fragment would be dropped"));
+            } else {
+                self.trace_packet(&packet, format_args!("Fragment dropped"));
+                self.drop_fragment(packet, DropReason::Pdr);
+                return;
+            }
+        }
+
+        match self.settings.fragment_ordering {
+            Some(capacity) => self.forward_in_order(packet, capacity),
+            None => self.forward_fragment(packet),
+        }
+    }
+
+    /// Forwards a fragment to the next hop by incrementing its routing header.
+    fn forward_fragment(&mut self, packet: Packet) {
+        // Increment hop index for forwarding
+        let mut forward_packet = packet.clone();
+        forward_packet.routing_header.hop_index += 1;
+
+        #[cfg(feature = "multipath")]
+        self.choose_multipath_hop(&mut forward_packet);
+
+        let next_hop_id =
+            forward_packet.routing_header.hops[forward_packet.routing_header.hop_index];
+        self.trace_packet(
+            &packet,
+            format_args!("Forwarding fragment to {}", next_hop_id),
+        );
+
+        if let Some(destination) = self
+            .settings
+            .drone_id_range
+            .as_ref()
+            .and_then(|range| destination_in_drone_range(&packet.routing_header.hops, range))
+        {
+            drone_error!(
+                self,
+                "Forwarding fragment of session {} to drone ID {}, route {:?}",
+                packet.session_id,
+                destination,
+                packet.routing_header.hops
+            );
+        }
+
+        if self.refuses_self_send(&forward_packet, next_hop_id) {
+            return;
+        }
+
+        // Send directly rather than through forward_packet, which panics if the neighbor is gone
+        let sent = match self.packet_send.get(&next_hop_id) {
+            Some(sender) if self.settings.forward_retries > 0 => {
+                self.send_with_retry(sender, forward_packet)
+            }
+            Some(sender) => sender.send(forward_packet).is_ok(),
+            None => false,
+        };
+        if sent {
+            self.record_decision(format_args!("Forwarded to {}", next_hop_id));
+            self.record_forward(next_hop_id);
+            self.track_session_progress(&packet);
+        } else {
+            drone_error!(
+                self,
+                "Failed to send fragment to {}, sending NACK",
+                next_hop_id
+            );
+            let nack_packet =
+                unreachable_hop_nack(&packet, next_hop_id, packet_fragment_index(&packet));
+            self.send_nack(nack_packet);
+        }
+    }
+
+    /// Sends a packet without blocking, retrying with a doubling delay while the channel is full.
+    ///
+    /// Returns `false` if the channel is disconnected or still full after the last retry.
+    fn send_with_retry(&self, sender: &Sender<Packet>, packet: Packet) -> bool {
+        let mut packet = match sender.try_send(packet) {
+            Ok(()) => return true,
+            Err(TrySendError::Disconnected(_)) => return false,
+            Err(TrySendError::Full(returned)) => returned,
+        };
+        let delays = forward_retry_delays(
+            self.settings.forward_retry_delay,
+            self.settings.forward_retries,
+        );
+        for delay in delays {
+            drone_status!(self, "Channel full, retrying send in {:?}", delay);
+            std::thread::sleep(delay);
+            packet = match sender.try_send(packet) {
+                Ok(()) => return true,
+                Err(TrySendError::Disconnected(_)) => return false,
+                Err(TrySendError::Full(returned)) => returned,
+            };
+        }
+        false
+    }
+
+    /// Drops a fragment, reporting it to the controller and sending a `Dropped` NACK to its source.
+    fn drop_fragment(&mut self, packet: Packet, reason: DropReason) {
+        self.record_drop(&packet, reason);
+
+        // Send dropped event
+        self.send_event(DroneEvent::PacketDropped(packet.clone()));
+
+        // Build NACK for dropped packet
+        let mut nack_packet = packet.clone();
+        let nack = Nack {
+            fragment_index: packet_fragment_index(&packet),
+            nack_type: NackType::Dropped,
+        };
+        nack_packet.pack_type = PacketType::Nack(nack);
+
+        // Create return route from current position to source
+        let mut hops: Vec<NodeId> =
+            packet.routing_header.hops[..=packet.routing_header.hop_index].to_vec();
+        hops.reverse();
+        nack_packet.routing_header = SourceRoutingHeader {
+            hop_index: 1, // Start at 1 since first hop is current node
+            hops: hops,
+        };
+
+        self.send_nack(nack_packet);
+    }
+
+    /// Returns `true` if the drone refuses to NACK a packet.
+    ///
+    /// Only fragments are meant to be NACKed, so NACKing any other packet is logged as an anomaly,
+    /// and refused if enabled; otherwise the NACK carries fragment index 0.
+    fn refuses_nack(&self, packet: &Packet) -> bool {
+        if matches!(packet.pack_type, PacketType::MsgFragment(_)) {
+            return false;
+        }
+        drone_error!(
+            self,
+            "Building NACK for {:?} packet of session {}, which is not a fragment",
+            PacketKind::from(&packet.pack_type),
+            packet.session_id
+        );
+        self.settings.refuse_non_fragment_nacks
+    }
+
+    /// Compares the fragment's `total_n_fragments` with the first value seen for its session.
+    ///
+    /// Sessions are only recorded while the check is enabled. Returns `false` if the fragment
+    /// must be discarded.
+    fn check_fragment_total(&mut self, packet: &Packet) -> bool {
+        if self.settings.fragment_total_check == FragmentTotalCheck::Off {
+            return true;
+        }
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            return true;
+        };
+
+        let (expected, last_seen) = self
+            .session_fragment_totals
+            .entry(packet.session_id)
+            .or_insert((fragment.total_n_fragments, Instant::now()));
+        *last_seen = Instant::now();
+        let expected = *expected;
+        self.evict_sessions();
+        if expected == fragment.total_n_fragments {
+            return true;
+        }
+
+        drone_error!(
+            self,
+            "Fragment {} of session {} reports {} fragments, expected {}",
+            fragment.fragment_index,
+            packet.session_id,
+            fragment.total_n_fragments,
+            expected
+        );
+        if self.settings.fragment_total_check == FragmentTotalCheck::Drop {
+            self.record_drop(packet, DropReason::FragmentTotalMismatch);
+            return false;
+        }
+        true
+    }
+
+    /// Counts a dropped packet and notifies the reason of the drop.
+    fn record_drop(&mut self, packet: &Packet, reason: DropReason) {
+        self.stats.dropped += 1;
+        self.dead_letter(packet);
+        self.record_decision(format_args!("Dropped: {:?}", reason));
+        self.notify(DroneNotification::PacketDropped {
+            session_id: packet.session_id,
+            fragment_index: packet_fragment_index(packet),
+            reason,
+        });
+    }
+
+    /// Determines whether a packet of the given kind should be dropped.
+    ///
+    /// Fragments use the packet drop rate (PDR), other kinds the rate set with
+    /// [`DroneControl::SetDropRate`]. Kinds without a rate are never dropped and leave the
+    /// random generator untouched.
+    /// When drop decision logging is enabled, every decision is logged with the random value behind it.
+    /// Returns `true` if the packet is to be dropped, or `false` otherwise.
+    fn should_drop(&mut self, kind: PacketKind) -> bool {
+        let rate = match kind {
+            PacketKind::Fragment => self.effective_pdr(),
+            _ => match self.settings.drop_rates.get(&kind) {
+                Some(rate) => *rate,
+                None => return false,
+            },
+        };
+
+        // A uniform roll in [0, 1) drops with probability exactly `rate`, so a rate of 1.0 always drops
+        let roll: f32 = self.get_random_generator().gen();
+        let drop = roll < rate;
+        self.record_decision(format_args!(
+            "Drop roll {:.4} against {} -> {}",
+            roll,
+            rate,
+            if drop { "drop" } else { "keep" }
+        ));
+        if self.settings.log_drop_decisions || self.is_log_boosted() {
+            drone_status!(
+                self,
+                "Drop decision for {:?}: roll {:.4} against threshold {} -> {}",
+                kind,
+                roll,
+                rate,
+                if drop { "drop" } else { "keep" }
+            );
+        }
+        drop
+    }
+
+    /// Evicts the per-session state that falls outside the session retention policy.
+    ///
+    /// Finding idle sessions means scanning all of them, so under [`SessionRetention::IdleFor`]
+    /// this only sweeps once half the idle duration has passed since the last sweep.
+    fn evict_sessions(&mut self) {
+        if let SessionRetention::IdleFor(idle) = self.settings.session_retention {
+            if self.last_session_sweep.elapsed() < idle / 2 {
+                return;
+            }
+        }
+        self.sweep_sessions();
+    }
+
+    /// Evicts the per-session state that falls outside the session retention policy right away.
+    fn sweep_sessions(&mut self) {
+        self.last_session_sweep = Instant::now();
+        let retention = self.settings.session_retention;
+        retain_sessions(&mut self.session_fragment_totals, retention);
+        retain_sessions(&mut self.session_progress, retention);
+        retain_sessions(&mut self.session_routes, retention);
+        self.evict_session_order();
+    }
+
+    /// Logs a fragment whose route differs from the last route seen for its session, if route
+    /// change detection is enabled.
+    fn detect_route_change(&mut self, packet: &Packet) {
+        if !self.settings.route_change_detection {
+            return;
+        }
+
+        let hops = &packet.routing_header.hops;
+        let previous = self
+            .session_routes
+            .insert(packet.session_id, (hops.clone(), Instant::now()));
+        if let Some((previous, _)) = previous.filter(|(previous, _)| previous != hops) {
+            drone_error!(
+                self,
+                "Route of session {} changed from {:?} to {:?}",
+                packet.session_id,
+                previous,
+                hops
+            );
+        }
+        self.evict_sessions();
+    }
+
+    /// Records a forwarded fragment, notifying the controller once the last fragment of its
+    /// session is forwarded if session completion events are enabled.
+    fn track_session_progress(&mut self, packet: &Packet) {
+        if !self.settings.session_completion {
+            return;
+        }
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            return;
+        };
+
+        let (forwarded, last_seen) = self
+            .session_progress
+            .entry(packet.session_id)
+            .or_insert_with(|| (HashSet::new(), Instant::now()));
+        forwarded.insert(fragment.fragment_index);
+        *last_seen = Instant::now();
+
+        if fragment.total_n_fragments.checked_sub(1) == Some(fragment.fragment_index) {
+            let forwarded = self
+                .session_progress
+                .remove(&packet.session_id)
+                .map_or(0, |(forwarded, _)| forwarded.len() as u64);
+            self.notify(DroneNotification::SessionCompleted {
+                session_id: packet.session_id,
+                forwarded,
+                total: fragment.total_n_fragments,
+            });
+        } else {
+            self.evict_sessions();
+        }
+    }
+
+    /// Derives the caps of the bounded state from a total budget, see [`DroneControl::SetStateBudget`].
+    fn set_state_budget(&mut self, budget: usize) {
+        let share = (budget / BUDGETED_STRUCTURES).max(1);
+        drone_status!(
+            self,
+            "State budget set to {} entries, {} per structure",
+            budget,
+            share
+        );
+
+        let max_tracked = self
+            .settings
+            .max_tracked_floods
+            .map_or(share, |max_tracked| max_tracked.min(share));
+        self.settings.max_tracked_floods = Some(max_tracked);
+        self.evict_tracked_floods();
+
+        let max_seen = self
+            .settings
+            .max_seen_floods
+            .map_or(share, |max_seen| max_seen.min(share));
+        self.settings.max_seen_floods = Some(max_seen);
+        self.evict_seen_floods();
+
+        let max_sessions = match self.settings.session_retention {
+            SessionRetention::MaxSessions(max_sessions) => max_sessions.min(share),
+            _ => share,
+        };
+        self.settings.session_retention = SessionRetention::MaxSessions(max_sessions);
+        self.evict_sessions();
+
+        // Flood response deduplication stays disabled if it was, it keeps no state then
+        if let Some(capacity) = self.settings.flood_response_dedup {
+            let capacity = capacity.min(share);
+            self.settings.flood_response_dedup = Some(capacity);
+            let excess = self.seen_flood_responses.len().saturating_sub(capacity);
+            self.seen_flood_responses.drain(..excess);
+        }
+    }
+
+    /// Sets the drop rate of routed packets of the given kind, see [`DroneControl::SetDropRate`].
+    ///
+    /// Rates outside `[0.0, 1.0]` are logged and ignored.
+    fn set_drop_rate(&mut self, kind: PacketKind, rate: Option<f32>) {
+        if kind == PacketKind::Fragment {
+            self.set_pdr(rate.unwrap_or(0.0));
+            return;
+        }
+
+        match rate {
+            Some(rate) if !(0.0..=1.0).contains(&rate) => {
+                drone_error!(self, "invalid drop rate for {:?}: {}", kind, rate);
+            }
+            Some(rate) => {
+                drone_status!(self, "Drop rate for {:?} set to {}", kind, rate);
+                self.settings.drop_rates.insert(kind, rate);
+            }
+            None => {
+                drone_status!(self, "Drop rate for {:?} cleared", kind);
+                self.settings.drop_rates.remove(&kind);
+            }
+        }
+    }
+
+    /// Sets the packet drop rate (PDR) for the drone.
+    ///
+    /// If the provided `new_pdr` is not within the range `[0.0, 1.0]`, an error is logged and the PDR remains unchanged.
+    /// Otherwise a [`DroneNotification::PdrChanged`] is sent. Either way, the value is recorded as
+    /// the configured PDR.
+    fn set_pdr(&mut self, new_pdr: f32) {
+        self.configured_pdr = new_pdr;
+        if new_pdr < 0.0 || new_pdr > 1.0 {
+            drone_error!(self, "invalid PDR value: {}", new_pdr);
+            return;
+        }
+        self.pdr = new_pdr;
+        self.notify(DroneNotification::PdrChanged(new_pdr));
+    }
+
+    /// Shuts the drone down once its maximum lifetime has elapsed.
+    ///
+    /// Unlike a crash, queued packets are handled normally before the drone exits.
+    fn expire(&mut self) {
+        drone_status!(self, "Maximum lifetime reached, shutting down");
+
+        for packet in self.take_arrived_packets() {
+            self.dispatch_packet(packet);
+        }
+        while let Ok(packet) = self.packet_recv.try_recv() {
+            self.dispatch_packet(packet);
+        }
+
+        self.should_exit = true;
+        self.exit_reason = Some(ExitReason::LifetimeExpired);
+        drone_status!(self, "Shut down");
+    }
+
+    /// Initiates the crash sequence for the drone.
+    ///
+    /// This method processes any remaining packets, updates the drone's state to indicate a crash,
+    /// and logs the crash events. Calling it again once the crash has started is a no-op.
+    ///
+    /// Packets are handled one at a time, so a flood request handled before the crash has already
+    /// reached all of its neighbors; only packets still queued are subject to crashing behavior.
+    /// Neighbors can keep forwarding to the drone while it drains: their sends succeed, and the
+    /// fragments only come back as `ErrorInRouting` NACKs once the drain reaches them. Packets the
+    /// drone would send to itself are discarded, so the drain ends even on self-referential routes.
+    /// Queued packets are drained in the order set with [`DroneControl::SetCrashDrainOrder`].
+    /// By default queued fragments are NACKed; [`DroneControl::SetCrashDrainMode`] can have them
+    /// forwarded instead. Queued flood requests are still handled unless disabled with
+    /// [`DroneControl::SetAnswerFloodsWhileCrashing`].
+    fn crash(&mut self) {
+        if self.crashing_behavior {
+            drone_status!(self, "Crash already in progress, ignoring");
+            return;
+        }
+
+        drone_status!(self, "Starting crash sequence");
+        drone_status!(
+            self,
+            "RNG fingerprint before drain: {:016x}",
+            self.rng_fingerprint()
+        );
+        self.crashing_behavior = true;
+        self.crashed_at = Some(Instant::now());
+        if self.settings.announce_crash {
+            self.notify(DroneNotification::Leaving {
+                neighbors: self.neighbors(),
+            });
+        }
+
+        match self.settings.crash_drain_order {
+            CrashDrainOrder::Fifo => {
+                for packet in self.take_arrived_packets() {
+                    self.dispatch_packet(packet);
+                }
+                while let Ok(packet) = self.packet_recv.try_recv() {
+                    self.dispatch_packet(packet);
+                }
+            }
+            CrashDrainOrder::ControlFirst => {
+                let arrived = self.take_arrived_packets();
+                let (fragments, control): (Vec<Packet>, Vec<Packet>) = arrived
+                    .into_iter()
+                    .chain(self.packet_recv.try_iter())
+                    .partition(|packet| matches!(packet.pack_type, PacketType::MsgFragment(_)));
+                for packet in control.into_iter().chain(fragments) {
+                    self.dispatch_packet(packet);
+                }
+            }
+        }
+
+        self.should_exit = true;
+        self.exit_reason = Some(ExitReason::Crashed);
+        drone_status!(self, "Crashed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::control::{EventKind, FloodFanOutPolicy, FloodInitiatorCheck};
+    use wg_2024::drone::Drone as _;
+    use wg_2024::packet::{Ack, FloodRequest, FloodResponse, Fragment};
+
+    use super::*;
+
+    /// A drone under test along with the other ends of its channels.
+    struct TestDrone<const N: usize> {
+        drone: Drone,
+        events: Receiver<DroneEvent>,
+        commands: Sender<DroneCommand>,
+        packets: Sender<Packet>,
+        /// Receivers of the neighbors, in the order their IDs were given.
+        neighbors: [Receiver<Packet>; N],
+    }
+
+    /// Creates a drone with an unbounded channel to each of the given neighbors.
+    fn new_test_drone<const N: usize>(
+        id: NodeId,
+        neighbor_ids: [NodeId; N],
+        pdr: f32,
+    ) -> TestDrone<N> {
+        wire_test_drone(
+            neighbor_ids,
+            |controller_send, controller_recv, packet_recv, packet_send| {
+                Drone::new(
+                    id,
+                    controller_send,
+                    controller_recv,
+                    packet_recv,
+                    packet_send,
+                    pdr,
+                )
+            },
+        )
+    }
+
+    /// Like [`new_test_drone`], drawing random numbers from `rng`.
+    fn new_test_drone_with_rng<const N: usize>(
+        id: NodeId,
+        neighbor_ids: [NodeId; N],
+        pdr: f32,
+        rng: StdRng,
+    ) -> TestDrone<N> {
+        wire_test_drone(
+            neighbor_ids,
+            |controller_send, controller_recv, packet_recv, packet_send| {
+                Drone::new_with_rng(
+                    id,
+                    controller_send,
+                    controller_recv,
+                    packet_recv,
+                    packet_send,
+                    pdr,
+                    rng,
+                )
+            },
+        )
+    }
+
+    /// Like [`new_test_drone`], building the drone from a configuration.
+    fn build_test_drone<const N: usize>(
+        config: &DroneConfig,
+        neighbor_ids: [NodeId; N],
+    ) -> TestDrone<N> {
+        wire_test_drone(
+            neighbor_ids,
+            |controller_send, controller_recv, packet_recv, packet_send| {
+                config.build(controller_send, controller_recv, packet_recv, packet_send)
+            },
+        )
+    }
+
+    /// Creates the channels of a test drone and builds it on them.
+    fn wire_test_drone<const N: usize>(
+        neighbor_ids: [NodeId; N],
+        build: impl FnOnce(
+            Sender<DroneEvent>,
+            Receiver<DroneCommand>,
+            Receiver<Packet>,
+            HashMap<NodeId, Sender<Packet>>,
+        ) -> Drone,
+    ) -> TestDrone<N> {
+        let (controller_send, events) = crossbeam_channel::unbounded();
+        let (commands, controller_recv) = crossbeam_channel::unbounded();
+        let (packets, packet_recv) = crossbeam_channel::unbounded();
+        let mut packet_send = HashMap::new();
+        let neighbors = neighbor_ids.map(|neighbor_id| {
+            let (send, recv) = crossbeam_channel::unbounded();
+            packet_send.insert(neighbor_id, send);
+            recv
+        });
+
+        TestDrone {
+            drone: build(controller_send, controller_recv, packet_recv, packet_send),
+            events,
+            commands,
+            packets,
+            neighbors,
+        }
+    }
+
+    /// Tests the creation of a Drone instance with the expected initial parameters.
+    #[test]
+    fn test_drone_creation() {
+        let TestDrone { drone, .. } = new_test_drone(1, [], 0.0);
+
+        assert_eq!(drone.id, 1);
+        assert_eq!(drone.pdr, 0.0);
+        assert!(drone.packet_send.is_empty());
+        assert!(drone.neighbors().is_empty());
+        assert!(!drone.is_neighbor(2));
+    }
+
+    /// Tests that a second Crash command neither re-drains the receiver nor resets the exit state.
+    #[test]
+    fn test_repeated_crash() {
+        let TestDrone {
+            mut drone,
+            packets: packet_send_self,
+            ..
+        } = new_test_drone(1, [], 0.0);
+
+        assert!(!drone.is_crashing());
+        drone.handle_command(Command::Drone(DroneCommand::Crash));
+        assert!(drone.crashing_behavior);
+        assert!(drone.is_crashing());
+        assert!(drone.should_exit);
+
+        // A packet arriving after the crash must not be drained by a second Crash
+        let packet = Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![2, 1],
+            },
+            session_id: 0,
+        };
+        packet_send_self.send(packet).unwrap();
+
+        drone.handle_command(Command::Drone(DroneCommand::Crash));
+        assert!(drone.should_exit);
+        assert_eq!(drone.packet_recv.len(), 1);
+    }
+
+    /// Tests that an Ack one hop before the end of its route is forwarded with a valid hop_index.
+    #[test]
+    fn test_ack_forward_at_route_boundary() {
+        let TestDrone {
+            mut drone,
+            events: controller_events,
+            neighbors: [neighbor_recv],
+            ..
+        } = new_test_drone(1, [3], 0.0);
+
+        let packet = Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![2, 1, 3],
+            },
+            session_id: 0,
+        };
+        drone.handle_routed_packet(packet);
+
+        let forwarded = neighbor_recv.try_recv().expect("Ack should be forwarded");
+        assert_eq!(forwarded.routing_header.hop_index, 2);
+        assert!(!controller_events
+            .try_iter()
+            .any(|event| matches!(event, DroneEvent::ControllerShortcut(_))));
+    }
+
+    /// Tests that direct forwarding only reaches neighbors and leaves the header untouched.
+    #[test]
+    fn test_forward_to_neighbor() {
+        let TestDrone {
+            mut drone,
+            neighbors: [neighbor_recv],
+            ..
+        } = new_test_drone(1, [3], 0.0);
+
+        let packet = Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![7, 8],
+            },
+            session_id: 0,
+        };
+
+        assert!(!drone.forward_to_neighbor(4, packet.clone()));
+        assert!(drone.forward_to_neighbor(3, packet.clone()));
+        assert_eq!(neighbor_recv.try_recv().unwrap(), packet);
+    }
+
+    /// Tests that a flood request reaching the max depth is answered instead of forwarded.
+    #[test]
+    fn test_max_flood_depth() {
+        // Chain 1 -> 11 -> 12 -> 13, this drone is 12
+        let TestDrone {
+            mut drone,
+            neighbors: [previous_recv, next_recv],
+            ..
+        } = new_test_drone(12, [11, 13], 0.0);
+        drone.handle_control(DroneControl::SetMaxFloodDepth(Some(3)));
+
+        let flood = Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id: 7,
+                initiator_id: 1,
+                path_trace: vec![(1, NodeType::Client), (11, NodeType::Drone)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        };
+        drone.dispatch_packet(flood);
+
+        assert!(next_recv.try_recv().is_err());
+        let response = previous_recv.try_recv().expect("Flood response expected");
+        assert_eq!(response.routing_header.hops, vec![12, 11, 1]);
+        match response.pack_type {
+            PacketType::FloodResponse(flood_response) => {
+                assert_eq!(flood_response.flood_id, 7);
+                assert_eq!(flood_response.path_trace.len(), 3);
+            }
+            other => panic!("Expected flood response, got {:?}", other),
+        }
+    }
+
+    /// Tests that captured NACKs carry the expected type and return route.
+    #[test]
+    fn test_capture_nacks() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_previous_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+        drone.capture_nacks();
+
+        let fragment = Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 3,
+                total_n_fragments: 4,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 1,
+        };
+        drone.process_packet_once(fragment);
+
+        assert_eq!(
+            drone.drain_generated_nacks(),
+            vec![(
+                NackType::ErrorInRouting(12),
+                SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![11, 1],
+                }
+            )]
+        );
+        assert!(drone.drain_generated_nacks().is_empty());
+    }
+
+    /// Tests that a fragment whose next hop was just removed is NACKed with ErrorInRouting.
+    #[test]
+    fn test_remove_sender_then_fragment() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+
+        drone.handle_command(Command::Drone(DroneCommand::RemoveSender(12)));
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 2,
+                total_n_fragments: 3,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 5,
+        });
+
+        assert!(next_recv.try_recv().is_err());
+        assert_eq!(
+            client_recv.try_recv().expect("NACK expected"),
+            Packet {
+                pack_type: PacketType::Nack(Nack {
+                    fragment_index: 2,
+                    nack_type: NackType::ErrorInRouting(12),
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![11, 1],
+                },
+                session_id: 5,
+            }
+        );
+    }
+
+    /// Tests which neighbors receive a flood under a fan-out cap for the deterministic policies.
+    #[test]
+    fn test_flood_fan_out_policy() {
+        let neighbor_ids = [2, 3, 4, 5];
+        let TestDrone {
+            mut drone,
+            neighbors,
+            ..
+        } = new_test_drone(1, neighbor_ids, 0.0);
+        drone.handle_control(DroneControl::SetFloodFanOut(Some(2)));
+
+        let flood = |flood_id| Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id,
+                initiator_id: 2,
+                path_trace: vec![(2, NodeType::Client)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        };
+        let received = || {
+            neighbor_ids
+                .into_iter()
+                .zip(&neighbors)
+                .filter(|(_, recv)| recv.try_recv().is_ok())
+                .map(|(id, _)| id)
+                .collect::<Vec<NodeId>>()
+        };
+
+        drone.process_packet_once(flood(1));
+        assert_eq!(received(), vec![3, 4]);
+
+        drone.handle_control(DroneControl::SetFloodFanOutPolicy(
+            FloodFanOutPolicy::RoundRobin,
+        ));
+        drone.process_packet_once(flood(2));
+        assert_eq!(received(), vec![3, 4]);
+        drone.process_packet_once(flood(3));
+        assert_eq!(received(), vec![4, 5]);
+    }
+
+    /// Tests that a drone built from a config entry only keeps its listed neighbors.
+    #[test]
+    fn test_drone_from_config() {
+        let config = wg_2024::config::Drone {
+            id: 11,
+            connected_node_ids: vec![2, 3, 9],
+            pdr: 0.25,
+        };
+        let TestDrone { drone, .. } = wire_test_drone(
+            [1, 2, 3, 4],
+            |controller_send, controller_recv, packet_recv, senders| {
+                Drone::from_config(
+                    &config,
+                    controller_send,
+                    controller_recv,
+                    packet_recv,
+                    &senders,
+                )
+            },
+        );
+
+        assert_eq!(drone.id, 11);
+        assert_eq!(drone.pdr, 0.25);
+        assert_eq!(drone.neighbors(), vec![2, 3]);
+    }
+
+    /// Tests that a PDR preset sets the PDR and nothing else.
+    #[test]
+    fn test_pdr_preset() {
+        let TestDrone { mut drone, .. } = new_test_drone(1, [], 0.5);
+        drone.handle_control(DroneControl::ApplyPdrPreset(PdrPreset::Wifi));
+        assert_eq!(drone.pdr, 0.05);
+        drone.handle_control(DroneControl::ApplyPdrPreset(PdrPreset::Clean));
+        assert_eq!(drone.pdr, 0.0);
+    }
+
+    /// Tests that only accepted PDR changes are notified.
+    #[test]
+    fn test_pdr_changed_notification() {
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone { drone, .. } = new_test_drone(1, [], 0.0);
+        let mut drone = drone.with_notification_channel(notification_send);
+
+        drone.handle_command(Command::Drone(DroneCommand::SetPacketDropRate(0.3)));
+        assert_eq!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::PdrChanged(0.3))
+        );
+
+        drone.handle_command(Command::Drone(DroneCommand::SetPacketDropRate(1.5)));
+        assert!(notification_recv.try_recv().is_err());
+        assert_eq!(drone.pdr, 0.3);
+    }
+
+    /// Tests that a packet with an enormous route is dropped without being processed.
+    #[test]
+    fn test_oversized_route_dropped() {
+        let TestDrone {
+            mut drone,
+            events: controller_events,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+        drone.capture_nacks();
+
+        let mut hops = vec![1, 11];
+        hops.resize(1_000_000, 12);
+        let exit = drone.process_packet_once(Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader { hop_index: 1, hops },
+            session_id: 0,
+        });
+
+        assert!(!exit);
+        assert!(client_recv.try_recv().is_err());
+        assert!(controller_events.try_recv().is_err());
+        assert!(drone.drain_generated_nacks().is_empty());
+    }
+
+    /// Tests that a drone built from a cloned config inherits the PDR and settings.
+    #[test]
+    fn test_clone_config() {
+        let TestDrone { drone, .. } = new_test_drone(5, [], 0.4);
+        let mut drone = drone
+            .with_max_lifetime(Duration::from_secs(30))
+            .with_heartbeat(Duration::from_secs(2))
+            .with_warm_up(3);
+        drone.handle_control(DroneControl::SetMaxFloodDepth(Some(4)));
+        // The replacement warms up again even if this drone already has
+        drone.warm_up_remaining = 0;
+        drone.seen_flood_ids.insert("1_1".to_string());
+
+        let TestDrone {
+            drone: replacement, ..
+        } = build_test_drone(&drone.clone_config(), []);
+
+        assert_eq!(replacement.id, 5);
+        assert_eq!(replacement.pdr, 0.4);
+        assert_eq!(replacement.settings.max_flood_depth, Some(4));
+        assert_eq!(replacement.max_lifetime, Some(Duration::from_secs(30)));
+        assert_eq!(replacement.heartbeat_interval, Some(Duration::from_secs(2)));
+        assert_eq!(replacement.warm_up_remaining, 3);
+        assert!(replacement.seen_flood_ids.is_empty());
+    }
+
+    /// Tests that a drone rebuilt from its configuration keeps an empty recent log buffer of the
+    /// same capacity.
+    #[test]
+    fn test_clone_config_recent_logs() {
+        let TestDrone { drone, .. } = new_test_drone(5, [], 0.0);
+        let mut drone = drone.with_recent_logs(2);
+        drone.handle_control(DroneControl::SetMaxFloodDepth(Some(4)));
+        assert_eq!(drone.recent_logs(10).len(), 1);
+
+        let TestDrone {
+            drone: mut replacement,
+            ..
+        } = build_test_drone(&drone.clone_config(), []);
+        assert!(replacement.recent_logs(10).is_empty());
+
+        for max_flood_depth in 1..=3 {
+            replacement.handle_control(DroneControl::SetMaxFloodDepth(Some(max_flood_depth)));
+        }
+        assert_eq!(replacement.recent_logs(10).len(), 2);
+    }
+
+    /// Tests that a flood handled before a crash is fully forwarded and queued fragments are then NACKed.
+    #[test]
+    fn test_crash_after_flood() {
+        let TestDrone {
+            mut drone,
+            packets: packet_send_self,
+            neighbors: [client_recv, d12_recv, d13_recv],
+            ..
+        } = new_test_drone(11, [1, 12, 13], 0.0);
+
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id: 1,
+                initiator_id: 1,
+                path_trace: vec![(1, NodeType::Client)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        });
+
+        packet_send_self
+            .send(Packet {
+                pack_type: PacketType::MsgFragment(Fragment {
+                    fragment_index: 0,
+                    total_n_fragments: 1,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12, 21],
+                },
+                session_id: 2,
+            })
+            .unwrap();
+        drone.handle_command(Command::Drone(DroneCommand::Crash));
+
+        assert!(matches!(
+            d12_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::FloodRequest(_))
+        ));
+        assert!(matches!(
+            d13_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::FloodRequest(_))
+        ));
+        assert!(d12_recv.try_recv().is_err());
+        assert!(matches!(
+            client_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::Nack(Nack {
+                nack_type: NackType::ErrorInRouting(11),
+                ..
+            }))
+        ));
+        assert!(drone.should_exit);
+    }
+
+    /// Tests that the NACK hook can rewrite and suppress NACKs.
+    #[test]
+    fn test_nack_hook() {
+        let TestDrone {
+            drone,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+
+        // Lie about the cause of routing errors, and suppress NACKs for session 2
+        let mut drone = drone.with_nack_hook(|nack| {
+            if let PacketType::Nack(n) = &mut nack.pack_type {
+                n.nack_type = NackType::Dropped;
+            }
+            nack.session_id != 2
+        });
+
+        let fragment = |session_id| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id,
+        };
+
+        drone.process_packet_once(fragment(1));
+        assert!(matches!(
+            client_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::Nack(Nack {
+                nack_type: NackType::Dropped,
+                ..
+            }))
+        ));
+
+        drone.process_packet_once(fragment(2));
+        assert!(client_recv.try_recv().is_err());
+    }
+
+    /// Tests that a flood request initiated by the drone itself is ignored.
+    #[test]
+    fn test_self_initiated_flood_ignored() {
+        let TestDrone {
+            mut drone,
+            neighbors: [neighbor_recv],
+            ..
+        } = new_test_drone(11, [12], 0.0);
+
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id: 3,
+                initiator_id: 11,
+                path_trace: vec![(11, NodeType::Drone), (13, NodeType::Drone)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        });
+
+        assert!(neighbor_recv.try_recv().is_err());
+        assert!(drone.seen_flood_ids.is_empty());
+    }
+
+    /// Tests that an Ack whose hop_index is past the end of its route is dropped and counted.
+    #[test]
+    fn test_ack_past_route_end() {
+        let TestDrone {
+            drone,
+            events: controller_events,
+            neighbors: [previous_recv, neighbor_recv],
+            ..
+        } = new_test_drone(1, [2, 3], 0.0);
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+        let mut drone = drone.with_notification_channel(notification_send);
+
+        for hop_index in [3, 4] {
+            let should_exit = drone.process_packet_once(Packet {
+                pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+                routing_header: SourceRoutingHeader {
+                    hop_index,
+                    hops: vec![2, 1, 3],
+                },
+                session_id: 0,
+            });
+            assert!(!should_exit);
+        }
+
+        assert!(previous_recv.try_recv().is_err());
+        assert!(neighbor_recv.try_recv().is_err());
+        assert!(controller_events.try_recv().is_err());
+        assert_eq!(drone.stats.dropped, 2);
+        assert_eq!(
+            notification_recv.try_iter().collect::<Vec<_>>(),
+            vec![
+                DroneNotification::PacketDropped {
+                    session_id: 0,
+                    fragment_index: 0,
+                    reason: DropReason::RouteOverrun,
+                };
+                2
+            ]
+        );
+    }
+
+    /// Tests that a drone built with a caller-provided RNG makes the same drop decisions as a clone of it.
+    #[test]
+    fn test_drone_with_rng() {
+        let rng = StdRng::seed_from_u64(42);
+        let mut expected_rng = rng.clone();
+
+        let TestDrone { mut drone, .. } = new_test_drone_with_rng(1, [], 0.5, rng);
+
+        for _ in 0..100 {
+            let expected = expected_rng.gen::<f32>() < 0.5;
+            assert_eq!(drone.should_drop(PacketKind::Fragment), expected);
+        }
+    }
+
+    /// Tests that the report aggregates received packets, NACKs, floods and the exit reason.
+    #[test]
+    fn test_report() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+
+        let flood = Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id: 1,
+                initiator_id: 1,
+                path_trace: vec![(1, NodeType::Client)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        };
+        drone.process_packet_once(flood.clone());
+        drone.process_packet_once(flood);
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 13, 21],
+            },
+            session_id: 1,
+        });
+        drone.handle_command(Command::Drone(DroneCommand::Crash));
+
+        let report = drone.report();
+        assert_eq!(report.received.flood_requests, 2);
+        assert_eq!(report.received.fragments, 1);
+        assert_eq!(report.received.total(), 3);
+        assert_eq!(report.floods_seen, 1);
+        assert_eq!(report.floods_deduplicated, 1);
+        assert_eq!(report.nacks_sent.error_in_routing, 1);
+        assert_eq!(report.dropped, 0);
+        assert_eq!(report.neighbors, vec![1, 12]);
+        assert_eq!(report.exit_reason, Some(ExitReason::Crashed));
+        assert!(report.to_string().contains("exit reason: Crashed"));
+    }
+
+    /// Tests that fragments towards a blackholed neighbor are NACKed while floods still reach it.
+    #[test]
+    fn test_blackholed_neighbor() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        drone.handle_control(DroneControl::BlackholeNeighbor(12));
+
+        let fragment = Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 1,
+                total_n_fragments: 2,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 4,
+        };
+        drone.process_packet_once(fragment.clone());
+
+        assert!(next_recv.try_recv().is_err());
+        assert!(matches!(
+            client_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::Nack(Nack {
+                nack_type: NackType::ErrorInRouting(12),
+                ..
+            }))
+        ));
+        assert!(drone.is_neighbor(12));
+
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id: 2,
+                initiator_id: 1,
+                path_trace: vec![(1, NodeType::Client)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        });
+        assert!(matches!(
+            next_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::FloodRequest(_))
+        ));
+
+        drone.handle_control(DroneControl::RestoreNeighbor(12));
+        drone.process_packet_once(fragment);
+        assert!(matches!(
+            next_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::MsgFragment(_))
+        ));
+    }
+
+    /// Tests that a fragment disagreeing with its session's total is only discarded in drop mode.
+    #[test]
+    fn test_fragment_total_mismatch() {
+        let TestDrone {
+            mut drone,
+            neighbors: [next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.0);
+
+        let fragment = |fragment_index, total_n_fragments| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index,
+                total_n_fragments,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 9,
+        };
+
+        drone.handle_control(DroneControl::SetFragmentTotalCheck(FragmentTotalCheck::Log));
+        drone.process_packet_once(fragment(0, 3));
+        drone.process_packet_once(fragment(1, 5));
+        assert_eq!(next_recv.try_iter().count(), 2);
+
+        drone.handle_control(DroneControl::SetFragmentTotalCheck(
+            FragmentTotalCheck::Drop,
+        ));
+        drone.process_packet_once(fragment(2, 5));
+        assert!(next_recv.try_recv().is_err());
+        drone.process_packet_once(fragment(2, 3));
+        assert!(next_recv.try_recv().is_ok());
+    }
+
+    /// Tests that processed packets show up in the throughput measurement.
+    #[test]
+    fn test_throughput() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.0);
+        assert_eq!(drone.throughput_pps(), 0.0);
+
+        for _ in 0..10 {
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12],
+                },
+                session_id: 0,
+            });
+        }
+        assert!(drone.throughput_pps() > 0.0);
+    }
+
+    /// Tests that deterministic flood sessions leave the drop decision stream untouched.
+    #[test]
+    fn test_deterministic_flood_session() {
+        let rng = StdRng::seed_from_u64(7);
+        let mut expected_rng = rng.clone();
+
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone_with_rng(11, [1], 0.5, rng);
+        drone.handle_control(DroneControl::SetDeterministicFloodSession(true));
+
+        // The client is the only neighbor, so every flood is answered with a response
+        for flood_id in 0..5 {
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::FloodRequest(FloodRequest {
+                    flood_id,
+                    initiator_id: 1,
+                    path_trace: vec![(1, NodeType::Client)],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 0,
+                    hops: vec![],
+                },
+                session_id: 0,
+            });
+            let response = client_recv.try_recv().expect("Flood response expected");
+            assert_eq!(response.session_id, flood_id);
+        }
+
+        for _ in 0..100 {
+            let expected = expected_rng.gen::<f32>() < 0.5;
+            assert_eq!(drone.should_drop(PacketKind::Fragment), expected);
+        }
+    }
+
+    /// Tests that the status reflects traffic, PDR, neighbors and uptime once running.
+    #[test]
+    fn test_status() {
+        let TestDrone {
+            mut drone,
+            commands: command_send,
+            packets: packet_send_self,
+            neighbors: [_next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.2);
+        assert_eq!(drone.status().uptime, None);
+
+        for _ in 0..3 {
+            packet_send_self
+                .send(Packet {
+                    pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+                    routing_header: SourceRoutingHeader {
+                        hop_index: 1,
+                        hops: vec![1, 11, 12],
+                    },
+                    session_id: 0,
+                })
+                .unwrap();
+        }
+        command_send.send(DroneCommand::Crash).unwrap();
+        drone.run();
+
+        let status = drone.status();
+        assert!(status.uptime.is_some());
+        assert_eq!(status.packets_received, 3);
+        assert!(status.throughput_pps > 0.0);
+        assert_eq!(status.pdr, 0.2);
+        assert_eq!(status.neighbor_count, 1);
+        assert!(status.crashing);
+    }
+
+    /// Tests that fair ordering handles messages in the order the drone received them.
+    #[test]
+    fn test_fair_ordering() {
+        let run_with = |fair_ordering| {
+            let TestDrone {
+                mut drone,
+                commands: command_send,
+                packets: packet_send_self,
+                neighbors: [_client_recv, next_recv],
+                ..
+            } = new_test_drone(11, [1, 12], 0.0);
+            drone.handle_control(DroneControl::SetFairOrdering(fair_ordering));
+            let send_fragment = |fragment_index| {
+                packet_send_self
+                    .send(Packet {
+                        pack_type: PacketType::MsgFragment(Fragment {
+                            fragment_index,
+                            total_n_fragments: 2,
+                            length: 128,
+                            data: [1; 128],
+                        }),
+                        routing_header: SourceRoutingHeader {
+                            hop_index: 1,
+                            hops: vec![1, 11, 12, 21],
+                        },
+                        session_id: 0,
+                    })
+                    .unwrap();
+            };
+
+            // Each message is received by the drone before the next one is sent
+            send_fragment(0);
+            if fair_ordering {
+                drone.collect_arrivals();
+            }
+            command_send
+                .send(DroneCommand::SetPacketDropRate(1.0))
+                .unwrap();
+            if fair_ordering {
+                drone.collect_arrivals();
+            }
+            send_fragment(1);
+            if fair_ordering {
+                drone.collect_arrivals();
+            }
+            command_send.send(DroneCommand::Crash).unwrap();
+            drone.run();
+
+            next_recv
+                .try_iter()
+                .map(|packet| packet_fragment_index(&packet))
+                .collect::<Vec<_>>()
+        };
+
+        // Biased: both commands run first, so the crash NACKs both fragments
+        assert_eq!(run_with(false), Vec::<u64>::new());
+        // Fair: the first fragment is forwarded before the PDR change, the second is dropped
+        assert_eq!(run_with(true), vec![0]);
+    }
+
+    /// Tests that collecting arrivals takes one packet per round and leaves the rest queued in the
+    /// packet channel.
+    #[test]
+    fn test_collect_arrivals_bounded() {
+        let TestDrone {
+            mut drone,
+            commands: command_send,
+            packets: packet_send_self,
+            ..
+        } = new_test_drone(11, [], 0.0);
+        let ack = Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12],
+            },
+            session_id: 0,
+        };
+        let queued = |drone: &Drone| {
+            drone
+                .arrivals
+                .iter()
+                .map(|arrival| match arrival {
+                    Arrival::Command(_) => 'c',
+                    Arrival::Control(_) => 'x',
+                    Arrival::Packet(_) => 'p',
+                })
+                .collect::<String>()
+        };
+
+        for _ in 0..10 {
+            packet_send_self.send(ack.clone()).unwrap();
+        }
+        drone.collect_arrivals();
+        assert_eq!(queued(&drone), "p");
+        assert_eq!(drone.packet_recv.len(), 9);
+
+        // No more packets are taken while one is still queued
+        drone.collect_arrivals();
+        assert_eq!(queued(&drone), "p");
+
+        // Each round takes a packet alongside a command
+        drone.arrivals.clear();
+        for _ in 0..2 {
+            command_send
+                .send(DroneCommand::SetPacketDropRate(0.0))
+                .unwrap();
+        }
+        drone.collect_arrivals();
+        assert_eq!(queued(&drone), "cpcp");
+        assert_eq!(drone.packet_recv.len(), 7);
+    }
+
+    /// Tests that the exit callback runs exactly once with the exit reason.
+    #[test]
+    fn test_on_exit() {
+        let (exit_send, exit_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            commands: command_send,
+            ..
+        } = new_test_drone(1, [], 0.0);
+        let mut drone = drone.with_on_exit(move |reason| exit_send.send(reason).unwrap());
+
+        command_send.send(DroneCommand::Crash).unwrap();
+        drone.run();
+        drone.run();
+
+        assert_eq!(exit_recv.try_recv(), Ok(ExitReason::Crashed));
+        assert!(exit_recv.try_recv().is_err());
+    }
+
+    /// Tests that an out-of-range PDR given at construction is clamped.
+    #[test]
+    fn test_invalid_initial_pdr() {
+        let drone_with_pdr = |pdr| new_test_drone(1, [], pdr).drone;
+
+        assert_eq!(drone_with_pdr(5.0).pdr, 1.0);
+        assert_eq!(drone_with_pdr(-0.5).pdr, 0.0);
+        assert_eq!(drone_with_pdr(f32::NAN).pdr, 0.0);
+        assert_eq!(drone_with_pdr(0.7).pdr, 0.7);
+    }
+
+    /// Tests that each packet kind can be dropped independently of the others.
+    #[test]
+    fn test_drop_rate_per_kind() {
+        let kinds = [
+            PacketKind::Fragment,
+            PacketKind::Ack,
+            PacketKind::Nack,
+            PacketKind::FloodRequest,
+            PacketKind::FloodResponse,
+        ];
+        let packet = |kind, index: u64| Packet {
+            pack_type: match kind {
+                PacketKind::Fragment => PacketType::MsgFragment(Fragment {
+                    fragment_index: index,
+                    total_n_fragments: 20,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                PacketKind::Ack => PacketType::Ack(Ack {
+                    fragment_index: index,
+                }),
+                PacketKind::Nack => PacketType::Nack(Nack {
+                    fragment_index: index,
+                    nack_type: NackType::Dropped,
+                }),
+                PacketKind::FloodRequest => PacketType::FloodRequest(FloodRequest {
+                    flood_id: index,
+                    initiator_id: 1,
+                    path_trace: vec![(1, NodeType::Client)],
+                }),
+                PacketKind::FloodResponse => PacketType::FloodResponse(FloodResponse {
+                    flood_id: index,
+                    path_trace: vec![(12, NodeType::Drone), (11, NodeType::Drone)],
+                }),
+            },
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12],
+            },
+            session_id: 0,
+        };
+
+        for dropped_kind in kinds {
+            let rng = StdRng::seed_from_u64(3);
+            let mut expected_rng = rng.clone();
+
+            let TestDrone {
+                mut drone,
+                neighbors: [_client_recv, next_recv],
+                ..
+            } = new_test_drone_with_rng(11, [1, 12], 0.0, rng);
+            drone.handle_control(DroneControl::SetDropRate(dropped_kind, Some(0.5)));
+
+            let mut expected_kept = 0;
+            for index in 0..20 {
+                for kind in kinds {
+                    drone.process_packet_once(packet(kind, index));
+                }
+                // Fragments always roll against the PDR, before the other kinds
+                if dropped_kind != PacketKind::Fragment {
+                    expected_rng.gen::<f32>();
+                }
+                if expected_rng.gen::<f32>() >= 0.5 {
+                    expected_kept += 1;
+                }
+            }
+
+            let mut received: HashMap<PacketKind, usize> = HashMap::new();
+            for forwarded in next_recv.try_iter() {
+                *received
+                    .entry(PacketKind::from(&forwarded.pack_type))
+                    .or_default() += 1;
+            }
+            for kind in kinds {
+                let expected = if kind == dropped_kind {
+                    expected_kept
+                } else {
+                    20
+                };
+                assert_eq!(
+                    received.get(&kind).copied().unwrap_or(0),
+                    expected,
+                    "{:?} with {:?} droppable",
+                    kind,
+                    dropped_kind
+                );
+            }
+        }
+    }
+
+    /// Tests listing blackholed neighbors across set, restore-one and restore-all.
+    #[test]
+    fn test_list_and_restore_blackholes() {
+        let TestDrone { mut drone, .. } = new_test_drone(11, [], 0.0);
+        assert!(drone.blackholed_neighbors().is_empty());
+
+        for neighbor in [14, 12, 13] {
+            drone.handle_control(DroneControl::BlackholeNeighbor(neighbor));
+        }
+        assert_eq!(drone.blackholed_neighbors(), vec![12, 13, 14]);
+
+        drone.handle_control(DroneControl::RestoreNeighbor(13));
+        assert_eq!(drone.blackholed_neighbors(), vec![12, 14]);
+
+        drone.handle_control(DroneControl::RestoreAllNeighbors);
+        assert!(drone.blackholed_neighbors().is_empty());
+    }
+
+    /// Tests that a drone with a maximum lifetime exits on its own without a Crash command.
+    #[test]
+    fn test_max_lifetime() {
+        let TestDrone {
+            drone,
+            commands: _command_send,
+            packets: _packet_send_self,
+            ..
+        } = new_test_drone(1, [], 0.0);
+        let mut drone = drone
+            .with_max_lifetime(Duration::from_millis(50))
+            .with_recent_logs(10);
+
+        let started_at = Instant::now();
+        drone.run();
+
+        assert!(started_at.elapsed() >= Duration::from_millis(50));
+        assert_eq!(drone.exit_reason(), Some(ExitReason::LifetimeExpired));
+        assert!(!drone.crashing_behavior);
+
+        drone.apply_command(DroneCommand::SetPacketDropRate(0.5));
+        assert_eq!(
+            drone.recent_logs(1),
+            vec!["Ignoring command received after lifetime expired".to_string()]
+        );
+        assert_eq!(drone.pdr, 0.0);
+    }
+
+    /// Tests that a lifetime too long to represent never elapses.
+    #[test]
+    fn test_max_lifetime_overflow() {
+        let TestDrone {
+            drone,
+            commands: command_send,
+            packets: _packet_send_self,
+            ..
+        } = new_test_drone(1, [], 0.0);
+        let mut drone = drone.with_max_lifetime(Duration::MAX);
+
+        command_send.send(DroneCommand::Crash).unwrap();
+        drone.run();
+
+        assert_eq!(drone.exit_reason(), Some(ExitReason::Crashed));
+    }
+
+    /// Tests that per-packet messages are only logged for the traced session.
+    #[test]
+    fn test_trace_session() {
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_recent_logs(100);
+        let fragment = |session_id| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id,
+        };
+        let traced = |drone: &Drone| {
+            drone
+                .recent_logs(100)
+                .into_iter()
+                .filter(|line| line.starts_with("[session"))
+                .collect::<Vec<String>>()
+        };
+
+        // Off by default
+        drone.process_packet_once(fragment(1));
+        assert!(traced(&drone).is_empty());
+
+        drone.handle_control(DroneControl::SetTraceSession(Some(2)));
+        drone.process_packet_once(fragment(1));
+        assert!(traced(&drone).is_empty());
+        drone.process_packet_once(fragment(2));
+        assert_eq!(
+            traced(&drone),
+            vec!["[session 2] Forwarding fragment to 12"]
+        );
+    }
+
+    /// Tests that the recent log buffer keeps only the newest lines.
+    #[test]
+    fn test_recent_logs() {
+        let TestDrone { mut drone, .. } = new_test_drone(1, [], 0.0);
+        drone.handle_control(DroneControl::BlackholeNeighbor(2));
+        assert!(drone.recent_logs(10).is_empty());
+
+        let mut drone = drone.with_recent_logs(2);
+        for neighbor in 3..=5 {
+            drone.handle_control(DroneControl::BlackholeNeighbor(neighbor));
+        }
+
+        assert_eq!(
+            drone.recent_logs(10),
+            vec!["Blackholing neighbor 4", "Blackholing neighbor 5"]
+        );
+        assert_eq!(drone.recent_logs(1), vec!["Blackholing neighbor 5"]);
+    }
+
+    /// Tests that a multipath group replaces the next hop of a fragment according to the weights.
+    #[cfg(feature = "multipath")]
+    #[test]
+    fn test_multipath_group() {
+        let TestDrone {
+            mut drone,
+            neighbors: [d12_recv, d13_recv],
+            ..
+        } = new_test_drone(11, [12, 13], 0.0);
+        drone.handle_control(DroneControl::SetMultipathGroup(12, vec![(12, 0), (13, 1)]));
+
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 0,
+        });
+
+        assert!(d12_recv.try_recv().is_err());
+        let forwarded = d13_recv
+            .try_recv()
+            .expect("Fragment expected on the alternative hop");
+        assert_eq!(forwarded.routing_header.hops, vec![1, 11, 13, 21]);
+    }
+
+    /// Tests that drops are notified with their reason.
+    #[test]
+    fn test_drop_reason_notification() {
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_notification_channel(notification_send);
+        drone.handle_control(DroneControl::SetFragmentTotalCheck(
+            FragmentTotalCheck::Drop,
+        ));
+
+        let fragment = |fragment_index, total_n_fragments| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index,
+                total_n_fragments,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 6,
+        };
+
+        drone.process_packet_once(fragment(0, 3));
+        drone.process_packet_once(fragment(1, 4));
+        assert_eq!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::PacketDropped {
+                session_id: 6,
+                fragment_index: 1,
+                reason: DropReason::FragmentTotalMismatch,
+            })
+        );
+
+        drone.handle_command(Command::Drone(DroneCommand::SetPacketDropRate(1.0)));
+        assert_eq!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::PdrChanged(1.0))
+        );
+        drone.process_packet_once(fragment(2, 3));
+        assert_eq!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::PacketDropped {
+                session_id: 6,
+                fragment_index: 2,
+                reason: DropReason::Pdr,
+            })
+        );
+    }
+
+    /// Tests that control-first draining forwards queued control packets before NACKing fragments.
+    #[test]
+    fn test_crash_drain_control_first() {
+        let TestDrone {
+            mut drone,
+            packets: packet_send_self,
+            neighbors: [client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        drone.handle_control(DroneControl::SetCrashDrainOrder(
+            CrashDrainOrder::ControlFirst,
+        ));
+
+        packet_send_self
+            .send(Packet {
+                pack_type: PacketType::MsgFragment(Fragment {
+                    fragment_index: 0,
+                    total_n_fragments: 1,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12, 21],
+                },
+                session_id: 1,
+            })
+            .unwrap();
+        packet_send_self
+            .send(Packet {
+                pack_type: PacketType::Ack(Ack { fragment_index: 3 }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![12, 11, 1],
+                },
+                session_id: 2,
+            })
+            .unwrap();
+        drone.handle_command(Command::Drone(DroneCommand::Crash));
+
+        let received: Vec<PacketType> = client_recv.try_iter().map(|p| p.pack_type).collect();
+        assert!(matches!(
+            received.as_slice(),
+            [
+                PacketType::Ack(_),
+                PacketType::Nack(Nack {
+                    nack_type: NackType::ErrorInRouting(11),
+                    ..
+                })
+            ]
+        ));
+    }
+
+    /// Tests the side-effect free routing check on valid, misrouted and out-of-bounds headers.
+    #[test]
+    fn test_is_routing_valid() {
+        let TestDrone {
+            drone,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+
+        let ack = |hop_index, hops| Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader { hop_index, hops },
+            session_id: 0,
+        };
+
+        assert!(drone.is_routing_valid(&ack(1, vec![1, 11, 12])));
+        assert!(!drone.is_routing_valid(&ack(1, vec![1, 13, 12])));
+        assert!(!drone.is_routing_valid(&ack(3, vec![1, 11, 12])));
+        assert!(client_recv.try_recv().is_err());
+    }
+
+    /// Tests that a replacement built from a crashed drone rejects commands during the cooldown.
+    #[test]
+    fn test_restart_cooldown() {
+        let respawn = |cooldown| {
+            let TestDrone { mut drone, .. } = new_test_drone(5, [], 0.0);
+            drone.handle_control(DroneControl::SetRestartCooldown(cooldown));
+            drone.handle_command(Command::Drone(DroneCommand::Crash));
+
+            let TestDrone {
+                drone: mut replacement,
+                ..
+            } = build_test_drone(&drone.clone_config(), []);
+            replacement.handle_command(Command::Drone(DroneCommand::AddSender(
+                6,
+                crossbeam_channel::unbounded().0,
+            )));
+            replacement.is_neighbor(6)
+        };
+
+        assert!(!respawn(Duration::from_secs(3600)));
+        assert!(!respawn(Duration::MAX));
+        assert!(respawn(Duration::ZERO));
+    }
+
+    /// Tests that a replacement still crashes when told to during the restart cooldown.
+    #[test]
+    fn test_restart_cooldown_crash() {
+        let TestDrone { mut drone, .. } = new_test_drone(5, [], 0.0);
+        drone.handle_control(DroneControl::SetRestartCooldown(Duration::from_secs(3600)));
+        drone.handle_command(Command::Drone(DroneCommand::Crash));
+
+        let TestDrone {
+            drone: mut replacement,
+            ..
+        } = build_test_drone(&drone.clone_config(), []);
+        replacement.handle_command(Command::Drone(DroneCommand::Crash));
+        assert!(replacement.crashing_behavior);
+        assert_eq!(replacement.exit_reason, Some(ExitReason::Crashed));
+    }
+
+    /// Tests that events reach every subscriber and that dropped subscribers are removed.
+    #[test]
+    fn test_event_subscribers() {
+        let (analytics_send, analytics_recv) = crossbeam_channel::unbounded();
+        let (gone_send, gone_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            mut drone,
+            events: controller_events,
+            ..
+        } = new_test_drone(11, [], 0.0);
+        drone.add_event_subscriber(analytics_send);
+        drone.add_event_subscriber(gone_send);
+        drop(gone_recv);
+
+        let ack = Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11],
+            },
+            session_id: 0,
+        };
+        drone.send_event(DroneEvent::ControllerShortcut(ack));
+
+        assert!(matches!(
+            controller_events.try_recv(),
+            Ok(DroneEvent::ControllerShortcut(_))
+        ));
+        assert!(matches!(
+            analytics_recv.try_recv(),
+            Ok(DroneEvent::ControllerShortcut(_))
+        ));
+        assert_eq!(drone.event_subscribers.len(), 1);
+    }
+
+    /// Tests that a duplicate flood request arriving after the validity window is ignored.
+    #[test]
+    fn test_flood_validity() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv, d12_recv, d13_recv],
+            ..
+        } = new_test_drone(11, [1, 12, 13], 0.0);
+        drone.handle_control(DroneControl::SetFloodValidity(Some(Duration::from_millis(
+            20,
+        ))));
+
+        let flood = |path_trace| Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id: 4,
+                initiator_id: 1,
+                path_trace,
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        };
+
+        drone.process_packet_once(flood(vec![(1, NodeType::Client)]));
+        assert!(d12_recv.try_recv().is_ok());
+        assert!(d13_recv.try_recv().is_ok());
+
+        std::thread::sleep(Duration::from_millis(40));
+        drone.process_packet_once(flood(vec![(1, NodeType::Client), (12, NodeType::Drone)]));
+
+        assert!(client_recv.try_recv().is_err());
+        assert!(d12_recv.try_recv().is_err());
+        assert!(d13_recv.try_recv().is_err());
+    }
+
+    /// Tests applying commands synchronously without channels or the event loop.
+    #[test]
+    fn test_apply_command() {
+        let TestDrone { mut drone, .. } = new_test_drone(1, [], 0.0);
+
+        drone.apply_command(DroneCommand::SetPacketDropRate(0.6));
+        drone.apply_command(DroneCommand::AddSender(2, crossbeam_channel::unbounded().0));
+        assert_eq!(drone.pdr, 0.6);
+        assert_eq!(drone.neighbors(), vec![2]);
+
+        drone.apply_command(DroneCommand::RemoveSender(2));
+        drone.apply_command(DroneCommand::Crash);
+        assert!(drone.neighbors().is_empty());
+        assert!(drone.should_exit);
+    }
+
+    /// Tests that resending the same channel for a neighbor is logged as redundant.
+    #[test]
+    fn test_redundant_add_sender() {
+        let (neighbor_send, _neighbor_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone { drone, .. } = new_test_drone(1, [], 0.0);
+        let mut drone = drone.with_recent_logs(10);
+
+        drone.apply_command(DroneCommand::AddSender(2, neighbor_send.clone()));
+        drone.apply_command(DroneCommand::AddSender(2, neighbor_send));
+        assert_eq!(
+            drone.recent_logs(1),
+            vec!["Redundant AddSender for 2, channel unchanged"]
+        );
+
+        drone.apply_command(DroneCommand::AddSender(2, crossbeam_channel::unbounded().0));
+        assert_eq!(drone.recent_logs(1), vec!["Replacing channel towards 2"]);
+        assert_eq!(drone.neighbors(), vec![2]);
+    }
+
+    /// Tests that the first fragments after startup are NACKed during the warm-up.
+    #[test]
+    fn test_warm_up() {
+        let TestDrone {
+            drone,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_warm_up(2);
+
+        for fragment_index in 0..3 {
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::MsgFragment(Fragment {
+                    fragment_index,
+                    total_n_fragments: 3,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12, 21],
+                },
+                session_id: 0,
+            });
+        }
+
+        let nacked: Vec<u64> = client_recv
+            .try_iter()
+            .map(|p| match p.pack_type {
+                PacketType::Nack(Nack {
+                    fragment_index,
+                    nack_type: NackType::Dropped,
+                }) => fragment_index,
+                other => panic!("Expected Dropped NACK, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(nacked, vec![0, 1]);
+        assert!(matches!(
+            next_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::MsgFragment(Fragment {
+                fragment_index: 2,
+                ..
+            }))
+        ));
+    }
+
+    /// Tests in-order forwarding: passthrough, reordering of early fragments and buffer overflow.
+    #[test]
+    fn test_fragment_ordering() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        drone.handle_control(DroneControl::SetFragmentOrdering(Some(2)));
+
+        let fragment = |session_id, fragment_index| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index,
+                total_n_fragments: 10,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id,
+        };
+        let forwarded = |next_recv: &Receiver<Packet>| -> Vec<u64> {
+            next_recv
+                .try_iter()
+                .map(|p| match p.pack_type {
+                    PacketType::MsgFragment(f) => f.fragment_index,
+                    other => panic!("Expected fragment, got {:?}", other),
+                })
+                .collect()
+        };
+
+        // In-order fragments pass straight through
+        drone.process_packet_once(fragment(1, 0));
+        drone.process_packet_once(fragment(1, 1));
+        assert_eq!(forwarded(&next_recv), vec![0, 1]);
+
+        // Early fragments wait for the missing one
+        drone.process_packet_once(fragment(2, 2));
+        drone.process_packet_once(fragment(2, 1));
+        assert!(forwarded(&next_recv).is_empty());
+        drone.process_packet_once(fragment(2, 0));
+        assert_eq!(forwarded(&next_recv), vec![0, 1, 2]);
+
+        // A third early fragment overflows the buffer of two
+        drone.process_packet_once(fragment(3, 1));
+        drone.process_packet_once(fragment(3, 2));
+        drone.process_packet_once(fragment(3, 3));
+        assert!(forwarded(&next_recv).is_empty());
+        assert!(matches!(
+            client_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::Nack(Nack {
+                fragment_index: 3,
+                nack_type: NackType::Dropped,
+            }))
+        ));
+
+        // Turning the mode off releases what is held back
+        drone.handle_control(DroneControl::SetFragmentOrdering(None));
+        let mut released = forwarded(&next_recv);
+        released.sort();
+        assert_eq!(released, vec![1, 2]);
+    }
+
+    /// Tests that in-order forwarding forgets completed sessions and releases the fragments held
+    /// by evicted ones.
+    #[test]
+    fn test_fragment_ordering_retention() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        drone.handle_control(DroneControl::SetFragmentOrdering(Some(4)));
+
+        let fragment = |session_id, fragment_index| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index,
+                total_n_fragments: 3,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id,
+        };
+
+        // A session is forgotten once its last fragment has passed
+        drone.process_packet_once(fragment(1, 2));
+        drone.process_packet_once(fragment(1, 0));
+        drone.process_packet_once(fragment(1, 1));
+        assert_eq!(next_recv.try_iter().count(), 3);
+        assert!(drone.session_order.is_empty());
+
+        // Evicting a session releases what it holds back
+        drone.process_packet_once(fragment(2, 1));
+        assert!(next_recv.try_recv().is_err());
+        drone.handle_control(DroneControl::SetSessionRetention(
+            SessionRetention::MaxSessions(1),
+        ));
+        std::thread::sleep(Duration::from_millis(1));
+        drone.process_packet_once(fragment(3, 2));
+        let released: Vec<(u64, u64)> = next_recv
+            .try_iter()
+            .map(|packet| (packet.session_id, packet_fragment_index(&packet)))
+            .collect();
+        assert_eq!(released, vec![(2, 1)]);
+        assert_eq!(drone.session_order.len(), 1);
+        assert!(drone.session_order.contains_key(&3));
+    }
+
+    /// Tests that routing checks use the ID set after construction.
+    #[test]
+    fn test_set_id() {
+        let TestDrone {
+            mut drone,
+            neighbors: [next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.0);
+        drone.set_id(42);
+
+        let ack = Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 42, 12],
+            },
+            session_id: 0,
+        };
+        assert!(drone.verify_routing(&ack));
+        drone.process_packet_once(ack);
+        assert!(next_recv.try_recv().is_ok());
+    }
+
+    /// Tests that heartbeats keep arriving while the drone idles.
+    #[test]
+    fn test_heartbeat() {
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            commands: _command_send,
+            packets: _packet_send_self,
+            ..
+        } = new_test_drone(1, [], 0.0);
+        let mut drone = drone
+            .with_notification_channel(notification_send)
+            .with_heartbeat(Duration::from_millis(10))
+            .with_max_lifetime(Duration::from_millis(100));
+        drone.run();
+
+        let heartbeats = notification_recv
+            .try_iter()
+            .filter(|notification| *notification == DroneNotification::Heartbeat)
+            .count();
+        assert!(heartbeats >= 2, "only {} heartbeats", heartbeats);
+    }
+
+    /// Tests both policies for a fragment whose final hop is the drone.
+    #[test]
+    fn test_destination_is_drone_policy() {
+        let TestDrone {
+            mut drone,
+            events: controller_events,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+
+        let fragment = Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11],
+            },
+            session_id: 3,
+        };
+
+        drone.process_packet_once(fragment.clone());
+        assert!(matches!(
+            client_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::Nack(Nack {
+                nack_type: NackType::DestinationIsDrone,
+                ..
+            }))
+        ));
+        assert!(controller_events.try_recv().is_err());
+
+        drone.handle_control(DroneControl::SetDestinationIsDronePolicy(
+            DestinationIsDronePolicy::ControllerShortcut,
+        ));
+        drone.process_packet_once(fragment.clone());
+        assert!(client_recv.try_recv().is_err());
+        assert!(matches!(
+            controller_events.try_recv(),
+            Ok(DroneEvent::ControllerShortcut(shortcut)) if shortcut == fragment
+        ));
+    }
+
+    /// Tests that the CSV metrics line matches the header and carries the counters.
+    #[test]
+    fn test_metrics_csv() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.5);
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12],
+            },
+            session_id: 0,
+        });
+
+        let line = drone.metrics_csv();
+        let columns: Vec<&str> = METRICS_CSV_HEADER.split(',').collect();
+        let values: Vec<&str> = line.split(',').collect();
+        assert_eq!(values.len(), columns.len());
+
+        let value = |name| values[columns.iter().position(|c| *c == name).unwrap()];
+        assert_eq!(value("id"), "11");
+        assert_eq!(value("acks"), "1");
+        assert_eq!(value("pdr"), "0.5");
+        assert_eq!(value("effective_pdr"), "0.5");
+        assert_eq!(value("neighbors"), "1");
+        assert_eq!(value("crashing"), "false");
+    }
+
+    /// Tests that drop decisions are logged during a logging boost only.
+    #[test]
+    fn test_boost_logging() {
+        let TestDrone {
+            drone,
+            neighbors: [_next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.0);
+        let mut drone = drone.with_recent_logs(100);
+        let fragment = Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12],
+            },
+            session_id: 0,
+        };
+        let drop_decisions = |drone: &Drone| {
+            drone
+                .recent_logs(100)
+                .iter()
+                .filter(|line| line.starts_with("Drop decision"))
+                .count()
+        };
+
+        drone.process_packet_once(fragment.clone());
+        assert_eq!(drop_decisions(&drone), 0);
+
+        drone.handle_control(DroneControl::BoostLogging(Duration::from_millis(50)));
+        drone.process_packet_once(fragment.clone());
+        assert_eq!(drop_decisions(&drone), 1);
+
+        std::thread::sleep(Duration::from_millis(60));
+        drone.process_packet_once(fragment.clone());
+        assert_eq!(drop_decisions(&drone), 1);
+
+        // A duration too long to represent boosts logging for good
+        drone.handle_control(DroneControl::BoostLogging(Duration::MAX));
+        drone.process_packet_once(fragment);
+        assert_eq!(drop_decisions(&drone), 2);
+        assert!(drone.log_boost.try_recv().is_err());
+    }
+
+    /// Tests that the events emitted while handling a packet are returned and still delivered.
+    #[test]
+    fn test_process_packet_capturing_events() {
+        let TestDrone {
+            drone,
+            events: controller_recv_events,
+            neighbors: [client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_warm_up(1);
+
+        let (should_exit, events) = drone.process_packet_capturing_events(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12],
+            },
+            session_id: 0,
+        });
+
+        assert!(!should_exit);
+        assert!(matches!(
+            client_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::Nack(Nack {
+                nack_type: NackType::Dropped,
+                ..
+            }))
+        ));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, DroneEvent::PacketDropped(_))));
+        assert_eq!(controller_recv_events.try_iter().count(), events.len());
+    }
+
+    /// Tests that measure-only mode counts drop decisions but forwards the fragments.
+    #[test]
+    fn test_measure_only() {
+        let rng = StdRng::seed_from_u64(7);
+        let mut expected_rng = rng.clone();
+
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone_with_rng(11, [1, 12], 0.5, rng);
+        drone.handle_control(DroneControl::SetMeasureOnly(true));
+
+        let expected = (0..20).filter(|_| expected_rng.gen::<f32>() < 0.5).count();
+        for fragment_index in 0..20 {
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::MsgFragment(Fragment {
+                    fragment_index,
+                    total_n_fragments: 20,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12],
+                },
+                session_id: 0,
+            });
+        }
+
+        assert_eq!(next_recv.try_iter().count(), 20);
+        assert!(client_recv.try_recv().is_err());
+        let report = drone.report();
+        assert_eq!(report.would_drop, expected);
+        assert_eq!(report.dropped, 0);
+    }
+
+    /// Tests that flood responses built close together are held back and flushed in one batch.
+    #[test]
+    fn test_flood_response_batching() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+        drone.handle_control(DroneControl::SetMaxFloodDepth(Some(1)));
+        drone.handle_control(DroneControl::SetFloodResponseBatching(Some(
+            Duration::from_secs(60),
+        )));
+
+        for flood_id in [1, 2, 2] {
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::FloodRequest(FloodRequest {
+                    flood_id,
+                    initiator_id: 1,
+                    path_trace: vec![(1, NodeType::Client)],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 0,
+                    hops: vec![],
+                },
+                session_id: 0,
+            });
+        }
+        assert!(client_recv.try_recv().is_err());
+
+        drone.flush_flood_responses();
+        let flood_ids: Vec<u64> = client_recv
+            .try_iter()
+            .map(|p| match p.pack_type {
+                PacketType::FloodResponse(response) => response.flood_id,
+                other => panic!("Expected FloodResponse, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(flood_ids, vec![1, 2]);
+
+        // A window too long to represent holds responses until batching is turned off
+        drone.handle_control(DroneControl::SetFloodResponseBatching(Some(Duration::MAX)));
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id: 3,
+                initiator_id: 1,
+                path_trace: vec![(1, NodeType::Client)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        });
+        assert!(client_recv.try_recv().is_err());
+        assert!(drone.flood_response_flush.try_recv().is_err());
+        drone.handle_control(DroneControl::SetFloodResponseBatching(None));
+        assert_eq!(client_recv.try_iter().count(), 1);
+    }
+
+    /// Tests that the RNG fingerprint matches across identically seeded drones until one draws.
+    #[test]
+    fn test_rng_fingerprint() {
+        let new_drone = || new_test_drone_with_rng(1, [], 0.5, StdRng::seed_from_u64(42)).drone;
+        let mut drone = new_drone();
+        let other = new_drone();
+
+        assert_eq!(drone.rng_fingerprint(), other.rng_fingerprint());
+        assert_eq!(drone.rng_fingerprint(), drone.rng_fingerprint());
+
+        drone.should_drop(PacketKind::Fragment);
+        assert_ne!(drone.rng_fingerprint(), other.rng_fingerprint());
+    }
+
+    /// Tests that a fragment whose next hop disconnected is answered with an `ErrorInRouting` NACK.
+    #[test]
+    fn test_forward_to_disconnected_neighbor() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        drop(next_recv);
+
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 3,
+                total_n_fragments: 4,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 5,
+        });
+
+        let nack = client_recv.try_recv().expect("NACK expected at the client");
+        assert_eq!(nack.routing_header.hops, vec![11, 1]);
+        assert_eq!(nack.session_id, 5);
+        assert!(matches!(
+            nack.pack_type,
+            PacketType::Nack(Nack {
+                fragment_index: 3,
+                nack_type: NackType::ErrorInRouting(12),
+            })
+        ));
+    }
+
+    /// Tests that the oldest tracked flood is evicted beyond the cap while floods keep being forwarded.
+    #[test]
+    fn test_max_tracked_floods() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let validity = Duration::from_secs(60);
+        drone.handle_control(DroneControl::SetFloodValidity(Some(validity)));
+        drone.handle_control(DroneControl::SetMaxTrackedFloods(Some(2)));
+
+        for flood_id in 1..=3 {
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::FloodRequest(FloodRequest {
+                    flood_id,
+                    initiator_id: 1,
+                    path_trace: vec![(1, NodeType::Client)],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 0,
+                    hops: vec![],
+                },
+                session_id: 0,
+            });
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(next_recv.try_iter().count(), 3);
+        let mut tracked: Vec<&String> = drone.flood_first_seen.keys().collect();
+        tracked.sort();
+        assert_eq!(tracked, vec!["2_1", "3_1"]);
+    }
+
+    /// Tests that events beyond the rate limit are dropped, except controller shortcuts.
+    #[test]
+    fn test_event_rate_limit() {
+        let TestDrone {
+            drone,
+            events: controller_events,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_warm_up(5);
+        drone.handle_control(DroneControl::SetEventRateLimit(Some(2)));
+        drone.handle_control(DroneControl::SetDestinationIsDronePolicy(
+            DestinationIsDronePolicy::ControllerShortcut,
+        ));
+
+        let fragment = |hops| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader { hop_index: 1, hops },
+            session_id: 0,
+        };
+        for _ in 0..5 {
+            drone.process_packet_once(fragment(vec![1, 11, 12]));
+        }
+        for _ in 0..3 {
+            drone.process_packet_once(fragment(vec![1, 11]));
+        }
+
+        let events: Vec<DroneEvent> = controller_events.try_iter().collect();
+        let dropped = events
+            .iter()
+            .filter(|event| matches!(event, DroneEvent::PacketDropped(_)))
+            .count();
+        let shortcuts = events
+            .iter()
+            .filter(|event| matches!(event, DroneEvent::ControllerShortcut(_)))
+            .count();
+        assert_eq!(dropped, 2);
+        assert_eq!(shortcuts, 3);
+        assert_eq!(drone.report().events_suppressed, 3);
+    }
+
+    /// Tests that a rejected or clamped PDR shows up as a configured PDR differing from the effective one.
+    #[test]
+    fn test_configured_and_effective_pdr() {
+        let TestDrone { mut drone, .. } = new_test_drone(1, [], 1.5);
+        assert_eq!(drone.configured_pdr(), 1.5);
+        assert_eq!(drone.effective_pdr(), 1.0);
+
+        drone.apply_command(DroneCommand::SetPacketDropRate(0.25));
+        assert_eq!(drone.configured_pdr(), 0.25);
+        assert_eq!(drone.effective_pdr(), 0.25);
+
+        drone.apply_command(DroneCommand::SetPacketDropRate(-0.5));
+        assert_eq!(drone.configured_pdr(), -0.5);
+        assert_eq!(drone.effective_pdr(), 0.25);
+    }
+
+    /// Tests that reports show the effective PDR next to the PDR, including warm-up and the floor.
+    #[test]
+    fn test_report_effective_pdr() {
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.1);
+        let mut drone = drone.with_warm_up(1);
+        assert_eq!(drone.report().effective_pdr, 1.0);
+        assert_eq!(drone.status().effective_pdr, 1.0);
+
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12],
+            },
+            session_id: 0,
+        });
+        assert_eq!(drone.report().effective_pdr, 0.1);
+
+        drone.handle_control(DroneControl::SetPdrFloor(0.3));
+        let report = drone.report();
+        assert_eq!((report.pdr, report.effective_pdr), (0.1, 0.3));
+        assert!(report.to_string().contains("pdr: 0.1 (effective 0.3)"));
+        assert_eq!(drone.status().effective_pdr, 0.3);
+    }
+
+    /// Tests that the drone reports its metrics on demand.
+    #[test]
+    fn test_report_metrics() {
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            neighbors: [_next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.0);
+        let mut drone = drone.with_notification_channel(notification_send);
+
+        for fragment_index in 0..2 {
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::Ack(Ack { fragment_index }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12],
+                },
+                session_id: 0,
+            });
+        }
+        drone.handle_control(DroneControl::ReportMetrics);
+
+        match notification_recv.try_recv() {
+            Ok(DroneNotification::Metrics(report)) => {
+                assert_eq!(report, drone.report());
+                assert_eq!(report.id, 11);
+                assert_eq!(report.received.acks, 2);
+                assert_eq!(report.neighbors, vec![12]);
+            }
+            other => panic!("Expected metrics notification, got {:?}", other),
+        }
+    }
+
+    /// Tests that a drone in transmit failure consumes packets without sending anything.
+    #[test]
+    fn test_transmit_failure() {
+        let TestDrone {
+            mut drone,
+            packets: packet_send,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        drone.handle_control(DroneControl::SetTransmitFailure(true));
+
+        packet_send
+            .send(Packet {
+                pack_type: PacketType::MsgFragment(Fragment {
+                    fragment_index: 0,
+                    total_n_fragments: 1,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12],
+                },
+                session_id: 0,
+            })
+            .unwrap();
+        packet_send
+            .send(Packet {
+                pack_type: PacketType::FloodRequest(FloodRequest {
+                    flood_id: 1,
+                    initiator_id: 1,
+                    path_trace: vec![(1, NodeType::Client)],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 0,
+                    hops: vec![],
+                },
+                session_id: 0,
+            })
+            .unwrap();
+        while let Ok(packet) = drone.packet_recv.try_recv() {
+            assert!(!drone.process_packet_once(packet));
+        }
+
+        assert_eq!(drone.inbound_queue_depth(), 0);
+        assert!(next_recv.try_recv().is_err());
+        assert!(client_recv.try_recv().is_err());
+        let report = drone.report();
+        assert_eq!(report.received.total(), 2);
+        assert_eq!(report.dropped, 1);
+    }
+
+    /// Tests that a repeated flood response is forwarded once with deduplication on.
+    #[test]
+    fn test_flood_response_dedup() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+        drone.handle_control(DroneControl::SetFloodResponseDedup(Some(8)));
+
+        let response = Packet {
+            pack_type: PacketType::FloodResponse(FloodResponse {
+                flood_id: 1,
+                path_trace: vec![
+                    (1, NodeType::Client),
+                    (11, NodeType::Drone),
+                    (12, NodeType::Drone),
+                ],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![12, 11, 1],
+            },
+            session_id: 0,
+        };
+        drone.process_packet_once(response.clone());
+        drone.process_packet_once(response);
+
+        assert_eq!(client_recv.try_iter().count(), 1);
+    }
+
+    /// Tests that flood response deduplication tells apart floods of different initiators sharing
+    /// a flood ID.
+    #[test]
+    fn test_flood_response_dedup_initiators() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv, other_client_recv],
+            ..
+        } = new_test_drone(11, [1, 2], 0.0);
+        drone.handle_control(DroneControl::SetFloodResponseDedup(Some(8)));
+
+        let response = |initiator| Packet {
+            pack_type: PacketType::FloodResponse(FloodResponse {
+                flood_id: 1,
+                path_trace: vec![
+                    (initiator, NodeType::Client),
+                    (11, NodeType::Drone),
+                    (12, NodeType::Drone),
+                ],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![12, 11, initiator],
+            },
+            session_id: 0,
+        };
+        drone.process_packet_once(response(1));
+        drone.process_packet_once(response(2));
+        drone.process_packet_once(response(2));
+
+        assert_eq!(client_recv.try_iter().count(), 1);
+        assert_eq!(other_client_recv.try_iter().count(), 1);
+    }
+
+    /// Tests the next hop reachability predicate.
+    #[test]
+    fn test_can_forward_to_next() {
+        let TestDrone {
+            mut drone,
+            neighbors: [next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.0);
+        let packet = |hops| Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader { hop_index: 1, hops },
+            session_id: 0,
+        };
+
+        assert!(drone.can_forward_to_next(&packet(vec![1, 11, 12])));
+        assert!(!drone.can_forward_to_next(&packet(vec![1, 11, 13])));
+        assert!(!drone.can_forward_to_next(&packet(vec![1, 11])));
+        assert!(next_recv.try_recv().is_err());
+
+        drone.handle_control(DroneControl::BlackholeNeighbor(12));
+        assert!(!drone.can_forward_to_next(&packet(vec![1, 11, 12])));
+    }
+
+    /// Tests that a crashing drone announces it is leaving, unless disabled.
+    #[test]
+    fn test_announce_crash() {
+        let crash = |announce| {
+            let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+            let TestDrone { drone, .. } = new_test_drone(11, [12, 1], 0.0);
+            let mut drone = drone.with_notification_channel(notification_send);
+            drone.handle_control(DroneControl::SetAnnounceCrash(announce));
+            drone.apply_command(DroneCommand::Crash);
+            notification_recv.try_iter().collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            crash(true),
+            vec![DroneNotification::Leaving {
+                neighbors: vec![1, 12]
+            }]
+        );
+        assert!(crash(false).is_empty());
+    }
+
+    /// Tests that a paused drone holds packets back, yet still crashes and drains them.
+    #[test]
+    fn test_pause_then_crash() {
+        let (control_send, control_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            commands: command_send,
+            packets: packet_send,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_control_channel(control_recv);
+
+        // Control messages are served before packets, so the drone pauses before the fragment
+        control_send.send(DroneControl::Pause).unwrap();
+        packet_send
+            .send(Packet {
+                pack_type: PacketType::MsgFragment(Fragment {
+                    fragment_index: 0,
+                    total_n_fragments: 1,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12],
+                },
+                session_id: 0,
+            })
+            .unwrap();
+        let handle = std::thread::spawn(move || drone.run());
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(next_recv.try_recv().is_err());
+        assert_eq!(packet_send.len(), 1);
+
+        command_send.send(DroneCommand::Crash).unwrap();
+        handle.join().unwrap();
+        assert!(next_recv.try_recv().is_err());
+        assert!(matches!(
+            client_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::Nack(Nack {
+                nack_type: NackType::ErrorInRouting(11),
+                ..
+            }))
+        ));
+    }
+
+    /// Tests that a PDR set while paused applies and shows up in the status.
+    #[test]
+    fn test_pause_then_set_pdr() {
+        let TestDrone { mut drone, .. } = new_test_drone(11, [], 0.0);
+
+        drone.handle_control(DroneControl::Pause);
+        assert!(drone.is_paused());
+        assert!(drone.status().paused);
+
+        drone.apply_command(DroneCommand::SetPacketDropRate(0.5));
+        drone.handle_control(DroneControl::Resume);
+        assert!(!drone.status().paused);
+        assert_eq!(drone.effective_pdr(), 0.5);
+    }
+
+    /// Tests that the first-seen time of a flood is recorded once timestamps are enabled.
+    #[test]
+    fn test_seen_flood_times() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let flood = |flood_id| Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id,
+                initiator_id: 1,
+                path_trace: vec![(1, NodeType::Client)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        };
+
+        drone.process_packet_once(flood(1));
+        assert!(drone.seen_flood_times().is_empty());
+
+        drone.handle_control(DroneControl::SetFloodTimestamps(true));
+        let before = Instant::now();
+        drone.process_packet_once(flood(2));
+        let after = Instant::now();
+        drone.process_packet_once(flood(2));
+
+        let seen = drone.seen_flood_times();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].0, "2_1");
+        assert!(before <= seen[0].1 && seen[0].1 <= after);
+    }
+
+    /// Tests both policies for a routed packet arriving with `hop_index` 0.
+    #[test]
+    fn test_hop_index_zero() {
+        let TestDrone {
+            mut drone,
+            events: controller_events,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let fragment = Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![1, 11, 12],
+            },
+            session_id: 0,
+        };
+
+        drone.process_packet_once(fragment.clone());
+        assert!(client_recv.try_recv().is_err());
+        assert!(next_recv.try_recv().is_err());
+        assert!(controller_events.try_recv().is_err());
+        assert_eq!(drone.report().dropped, 1);
+
+        drone.handle_control(DroneControl::SetHopIndexZeroPolicy(
+            HopIndexZeroPolicy::ControllerShortcut,
+        ));
+        drone.process_packet_once(fragment.clone());
+        assert!(client_recv.try_recv().is_err());
+        assert!(matches!(
+            controller_events.try_recv(),
+            Ok(DroneEvent::ControllerShortcut(shortcut)) if shortcut == fragment
+        ));
+    }
+
+    /// Tests that disabling dropped packet events keeps the NACKs but sends no event.
+    #[test]
+    fn test_disable_packet_dropped_events() {
+        let TestDrone {
+            drone,
+            events: controller_events,
+            neighbors: [client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_warm_up(1);
+        drone.handle_control(DroneControl::SetEventEnabled(
+            EventKind::PacketDropped,
+            false,
+        ));
+        drone.handle_control(DroneControl::SetEventEnabled(
+            EventKind::ControllerShortcut,
+            false,
+        ));
+
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12],
+            },
+            session_id: 0,
+        });
+
+        assert!(matches!(
+            client_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::Nack(Nack {
+                nack_type: NackType::Dropped,
+                ..
+            }))
+        ));
+        assert!(!controller_events
+            .try_iter()
+            .any(|event| matches!(event, DroneEvent::PacketDropped(_))));
+        assert_eq!(
+            drone.settings.disabled_events,
+            HashSet::from([EventKind::PacketDropped])
+        );
+    }
+
+    /// Tests that a drone built with an oversized neighbor map caps its flood fan-out.
+    #[test]
+    fn test_large_neighbor_map() {
+        let neighbor_ids: [NodeId; 100] = std::array::from_fn(|i| 100 + i as NodeId);
+        let TestDrone {
+            mut drone,
+            neighbors,
+            ..
+        } = new_test_drone(11, neighbor_ids, 0.0);
+        assert_eq!(
+            drone.settings.flood_fan_out,
+            Some(LARGE_NEIGHBOR_FLOOD_FAN_OUT)
+        );
+
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id: 1,
+                initiator_id: 1,
+                path_trace: vec![(1, NodeType::Client)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        });
+        assert_eq!(
+            neighbors
+                .iter()
+                .map(|neighbor_recv| neighbor_recv.try_iter().count())
+                .sum::<usize>(),
+            LARGE_NEIGHBOR_FLOOD_FAN_OUT
+        );
+    }
+
+    /// Tests that adding senders past the large neighbor count caps the flood fan-out with a
+    /// warning, unless a cap was set explicitly.
+    #[test]
+    fn test_large_neighbor_map_add_sender() {
+        let (neighbor_send, _neighbor_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone { drone, .. } = new_test_drone(11, [], 0.0);
+        let mut drone = drone.with_recent_logs(200);
+        for id in 100..=100 + LARGE_NEIGHBOR_COUNT as NodeId {
+            assert_eq!(drone.settings.flood_fan_out, None);
+            drone.apply_command(DroneCommand::AddSender(id, neighbor_send.clone()));
+        }
+        assert_eq!(
+            drone.settings.flood_fan_out,
+            Some(LARGE_NEIGHBOR_FLOOD_FAN_OUT)
+        );
+        assert_eq!(
+            drone.recent_logs(1),
+            vec![format!(
+                "{} neighbors (more than {}), capping flood fan-out at {}",
+                LARGE_NEIGHBOR_COUNT + 1,
+                LARGE_NEIGHBOR_COUNT,
+                LARGE_NEIGHBOR_FLOOD_FAN_OUT
+            )]
+        );
+
+        // The automatic cap is not part of the configuration, a replacement decides on its own
+        let config = drone.clone_config();
+        let TestDrone {
+            drone: replacement, ..
+        } = build_test_drone(&config, [1, 2]);
+        assert_eq!(replacement.settings.flood_fan_out, None);
+        let TestDrone {
+            drone: replacement, ..
+        } = build_test_drone(
+            &config,
+            std::array::from_fn::<NodeId, 100, _>(|i| i as NodeId),
+        );
+        assert_eq!(
+            replacement.settings.flood_fan_out,
+            Some(LARGE_NEIGHBOR_FLOOD_FAN_OUT)
+        );
+
+        // Back at the threshold, the automatic cap is lifted
+        drone.apply_command(DroneCommand::RemoveSender(100));
+        assert_eq!(drone.settings.flood_fan_out, None);
+
+        // An explicit choice, even no cap, is kept
+        let TestDrone { mut drone, .. } = new_test_drone(11, [], 0.0);
+        drone.handle_control(DroneControl::SetFloodFanOut(None));
+        for id in 100..=100 + LARGE_NEIGHBOR_COUNT as NodeId {
+            drone.apply_command(DroneCommand::AddSender(id, neighbor_send.clone()));
+        }
+        assert_eq!(drone.settings.flood_fan_out, None);
+        drone.handle_control(DroneControl::SetFloodFanOut(Some(2)));
+        drone.apply_command(DroneCommand::RemoveSender(100));
+        assert_eq!(drone.settings.flood_fan_out, Some(2));
+    }
+
+    /// Tests that a flood request is never forwarded back to the neighbor it came from.
+    #[test]
+    fn test_flood_not_sent_back() {
+        for fan_out in [None, Some(2)] {
+            let TestDrone {
+                mut drone,
+                neighbors: [previous_recv, next_recv],
+                ..
+            } = new_test_drone(12, [11, 13], 0.0);
+            drone.handle_control(DroneControl::SetFloodFanOut(fan_out));
+
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::FloodRequest(FloodRequest {
+                    flood_id: 1,
+                    initiator_id: 1,
+                    path_trace: vec![(1, NodeType::Client), (11, NodeType::Drone)],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 0,
+                    hops: vec![],
+                },
+                session_id: 0,
+            });
+
+            assert!(previous_recv.try_recv().is_err(), "fan-out {:?}", fan_out);
+            assert!(matches!(
+                next_recv.try_recv().map(|p| p.pack_type),
+                Ok(PacketType::FloodRequest(_))
+            ));
+        }
+    }
+
+    /// Tests that forwarding to a neighbor updates its last activity time.
+    #[test]
+    fn test_neighbor_last_activity() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let fragment = Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12],
+            },
+            session_id: 0,
+        };
+
+        drone.process_packet_once(fragment.clone());
+        assert!(drone.neighbor_last_activity().is_empty());
+
+        drone.handle_control(DroneControl::SetNeighborActivityTracking(true));
+        let before = Instant::now();
+        drone.process_packet_once(fragment.clone());
+        let first = drone.neighbor_last_activity()[&12];
+        assert!(first >= before);
+        assert!(!drone.neighbor_last_activity().contains_key(&1));
+
+        std::thread::sleep(Duration::from_millis(5));
+        drone.process_packet_once(fragment);
+        assert!(drone.neighbor_last_activity()[&12] > first);
+    }
+
+    /// Tests both policies for a packet at its destination when the controller channel is closed.
+    #[test]
+    fn test_shortcut_failure() {
+        let TestDrone {
+            mut drone,
+            events: controller_events,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+        drop(controller_events);
+        let ack = Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11],
+            },
+            session_id: 3,
+        };
+
+        assert!(!drone.process_packet_once(ack.clone()));
+        assert!(client_recv.try_recv().is_err());
+
+        drone.handle_control(DroneControl::SetShortcutFailurePolicy(
+            ShortcutFailurePolicy::ReturnToSender,
+        ));
+        assert!(!drone.process_packet_once(ack));
+        let returned = client_recv
+            .try_recv()
+            .expect("Packet expected back at the client");
+        assert_eq!(returned.routing_header.hops, vec![11, 1]);
+        assert_eq!(returned.session_id, 3);
+    }
+
+    /// Tests that a packet shortcut to the controller at the end of its route is not also
+    /// forwarded to a neighbor.
+    #[test]
+    fn test_shortcut_not_forwarded() {
+        let TestDrone {
+            mut drone,
+            events: controller_events,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+
+        assert!(!drone.process_packet_once(Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 0 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11],
+            },
+            session_id: 3,
+        }));
+
+        assert!(matches!(
+            controller_events.try_recv(),
+            Ok(DroneEvent::ControllerShortcut(_))
+        ));
+        assert!(client_recv.try_recv().is_err());
+        assert!(next_recv.try_recv().is_err());
+    }
+
+    /// Tests that only processed floods are reported as seen.
+    #[test]
+    fn test_has_seen_flood() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id: 7,
+                initiator_id: 1,
+                path_trace: vec![(1, NodeType::Client)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        });
+
+        assert!(drone.has_seen_flood(7, 1));
+        assert!(!drone.has_seen_flood(8, 1));
+        assert!(!drone.has_seen_flood(7, 2));
+    }
+
+    /// Tests that per-session state is evicted according to the retention policy.
+    #[test]
+    fn test_session_retention() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.0);
+        drone.handle_control(DroneControl::SetFragmentTotalCheck(FragmentTotalCheck::Log));
+        drone.handle_control(DroneControl::SetSessionRetention(
+            SessionRetention::MaxSessions(3),
+        ));
+
+        let send_fragment = |drone: &mut Drone, session_id| {
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::MsgFragment(Fragment {
+                    fragment_index: 0,
+                    total_n_fragments: 1,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12],
+                },
+                session_id,
+            });
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        let tracked = |drone: &Drone| {
+            let mut sessions: Vec<u64> = drone.session_fragment_totals.keys().copied().collect();
+            sessions.sort();
+            sessions
+        };
+
+        for session_id in 0..5 {
+            send_fragment(&mut drone, session_id);
+        }
+        assert_eq!(tracked(&drone), vec![2, 3, 4]);
+
+        // Session 2 becomes the most recently used, so session 3 goes next
+        send_fragment(&mut drone, 2);
+        send_fragment(&mut drone, 5);
+        assert_eq!(tracked(&drone), vec![2, 4, 5]);
+
+        drone.handle_control(DroneControl::SetSessionRetention(
+            SessionRetention::IdleFor(Duration::from_millis(20)),
+        ));
+        std::thread::sleep(Duration::from_millis(30));
+        send_fragment(&mut drone, 6);
+        assert_eq!(tracked(&drone), vec![6]);
+
+        // Idle sessions are only swept once half the idle duration has passed since the last sweep
+        drone.handle_control(DroneControl::SetSessionRetention(
+            SessionRetention::IdleFor(Duration::from_millis(40)),
+        ));
+        std::thread::sleep(Duration::from_millis(50));
+        drone.last_session_sweep = Instant::now();
+        send_fragment(&mut drone, 7);
+        assert_eq!(tracked(&drone), vec![6, 7]);
+
+        std::thread::sleep(Duration::from_millis(25));
+        send_fragment(&mut drone, 8);
+        assert_eq!(tracked(&drone), vec![7, 8]);
+    }
+
+    /// Tests that forwarded and originated NACKs are copied to the controller with mirroring on.
+    #[test]
+    fn test_nack_mirroring() {
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+        let mut drone = drone.with_notification_channel(notification_send);
+        drone.handle_control(DroneControl::SetNackMirroring(true));
+
+        let nack = Packet {
+            pack_type: PacketType::Nack(Nack {
+                fragment_index: 2,
+                nack_type: NackType::Dropped,
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![12, 11, 1],
+            },
+            session_id: 4,
+        };
+        drone.process_packet_once(nack);
+
+        let forwarded = client_recv.try_recv().expect("NACK expected at the client");
+        assert_eq!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::NackCopy {
+                nack: forwarded,
+                originated: false,
+            })
+        );
+
+        // A fragment towards an unknown neighbor makes the drone originate a NACK
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 13],
+            },
+            session_id: 5,
+        });
+        assert!(matches!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::NackCopy {
+                originated: true,
+                ..
+            })
+        ));
+    }
+
+    /// Tests that fragments queued before a crash are NACKed by default and forwarded in forward mode.
+    #[test]
+    fn test_crash_drain_mode() {
+        for (mode, forwarded) in [(None, false), (Some(CrashDrainMode::Forward), true)] {
+            let TestDrone {
+                mut drone,
+                packets: packet_send_self,
+                neighbors: [client_recv, next_recv],
+                ..
+            } = new_test_drone(11, [1, 12], 0.0);
+            if let Some(mode) = mode {
+                drone.handle_control(DroneControl::SetCrashDrainMode(mode));
+            }
+
+            for fragment_index in 0..2 {
+                packet_send_self
+                    .send(Packet {
+                        pack_type: PacketType::MsgFragment(Fragment {
+                            fragment_index,
+                            total_n_fragments: 2,
+                            length: 128,
+                            data: [1; 128],
+                        }),
+                        routing_header: SourceRoutingHeader {
+                            hop_index: 1,
+                            hops: vec![1, 11, 12, 21],
+                        },
+                        session_id: 1,
+                    })
+                    .unwrap();
+            }
+            drone.handle_command(Command::Drone(DroneCommand::Crash));
+
+            assert!(drone.should_exit);
+            if forwarded {
+                assert_eq!(next_recv.try_iter().count(), 2);
+                assert!(client_recv.try_recv().is_err());
+            } else {
+                assert!(next_recv.try_recv().is_err());
+                let nacks: Vec<Packet> = client_recv.try_iter().collect();
+                assert_eq!(nacks.len(), 2);
+                assert!(nacks.iter().all(|p| matches!(
+                    p.pack_type,
+                    PacketType::Nack(Nack {
+                        nack_type: NackType::ErrorInRouting(11),
+                        ..
+                    })
+                )));
+            }
+        }
+    }
+
+    /// Tests that forwarding a fragment destined for a drone ID is flagged when the check is on.
+    #[test]
+    fn test_drone_id_range_warning() {
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_recent_logs(10);
+        let fragment = |destination| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, destination],
+            },
+            session_id: 3,
+        };
+
+        // Off by default
+        drone.process_packet_once(fragment(13));
+        assert!(next_recv.try_recv().is_ok());
+        assert!(drone
+            .recent_logs(10)
+            .iter()
+            .all(|l| !l.contains("drone ID")));
+
+        drone.handle_control(DroneControl::SetDroneIdRange(Some(11..=20)));
+        drone.process_packet_once(fragment(21));
+        assert!(drone
+            .recent_logs(10)
+            .iter()
+            .all(|l| !l.contains("drone ID")));
+
+        drone.process_packet_once(fragment(13));
+        assert_eq!(next_recv.try_iter().count(), 2);
+        assert_eq!(
+            drone.recent_logs(1),
+            vec!["Forwarding fragment of session 3 to drone ID 13, route [1, 11, 12, 13]"]
+        );
+    }
+
+    /// Tests that the observed drop count converges to the expected count, including at the bounds.
+    #[test]
+    fn test_expected_drops() {
+        for (pdr, n) in [(0.0, 1000), (0.3, 10_000), (0.55, 10_000), (1.0, 1000)] {
+            let TestDrone { mut drone, .. } =
+                new_test_drone_with_rng(1, [], pdr, StdRng::seed_from_u64(7));
+
+            let dropped = (0..n)
+                .filter(|_| drone.should_drop(PacketKind::Fragment))
+                .count() as f64;
+            let expected = Drone::expected_drops(pdr, n);
+            let tolerance = 4.0 * (n as f64 * pdr as f64 * (1.0 - pdr as f64)).sqrt();
+            assert!(
+                (dropped - expected).abs() <= tolerance,
+                "PDR {}: {} drops, expected {} +- {}",
+                pdr,
+                dropped,
+                expected,
+                tolerance
+            );
+        }
+    }
+
+    /// Tests that a crashing drone answers drained floods by default, and ignores them when disabled.
+    #[test]
+    fn test_floods_while_crashing() {
+        for answer in [true, false] {
+            let TestDrone {
+                mut drone,
+                packets: packet_send_self,
+                neighbors: [client_recv, next_recv],
+                ..
+            } = new_test_drone(11, [1, 12], 0.0);
+            if !answer {
+                drone.handle_control(DroneControl::SetAnswerFloodsWhileCrashing(false));
+            }
+
+            packet_send_self
+                .send(Packet {
+                    pack_type: PacketType::FloodRequest(FloodRequest {
+                        flood_id: 4,
+                        initiator_id: 1,
+                        path_trace: vec![(1, NodeType::Client)],
+                    }),
+                    routing_header: SourceRoutingHeader {
+                        hop_index: 0,
+                        hops: vec![],
+                    },
+                    session_id: 0,
+                })
+                .unwrap();
+            drone.handle_command(Command::Drone(DroneCommand::Crash));
+
+            assert_eq!(next_recv.try_recv().is_ok(), answer);
+            assert!(client_recv.try_recv().is_err());
+            assert_eq!(drone.has_seen_flood(4, 1), answer);
+        }
+    }
+
+    /// Tests that a state budget caps every budgeted structure, including state kept beforehand.
+    #[test]
+    fn test_state_budget() {
+        let TestDrone {
+            mut drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        drone.handle_control(DroneControl::SetFloodValidity(Some(Duration::from_secs(
+            60,
+        ))));
+        drone.handle_control(DroneControl::SetFragmentTotalCheck(FragmentTotalCheck::Log));
+        drone.handle_control(DroneControl::SetFloodResponseDedup(Some(100)));
+        drone.handle_control(DroneControl::SetFragmentOrdering(Some(4)));
+
+        let send_traffic = |drone: &mut Drone, first_id: u64| {
+            for id in first_id..first_id + 4 {
+                drone.process_packet_once(Packet {
+                    pack_type: PacketType::FloodRequest(FloodRequest {
+                        flood_id: id,
+                        initiator_id: 1,
+                        path_trace: vec![(1, NodeType::Client)],
+                    }),
+                    routing_header: SourceRoutingHeader {
+                        hop_index: 0,
+                        hops: vec![],
+                    },
+                    session_id: 0,
+                });
+                drone.process_packet_once(Packet {
+                    pack_type: PacketType::MsgFragment(Fragment {
+                        // Held back waiting for fragment 0, so the session stays ordered
+                        fragment_index: 1,
+                        total_n_fragments: 2,
+                        length: 128,
+                        data: [1; 128],
+                    }),
+                    routing_header: SourceRoutingHeader {
+                        hop_index: 1,
+                        hops: vec![1, 11, 12, 21],
+                    },
+                    session_id: id,
+                });
+                drone.process_packet_once(Packet {
+                    pack_type: PacketType::FloodResponse(FloodResponse {
+                        flood_id: id,
+                        path_trace: vec![(1, NodeType::Client), (11, NodeType::Drone)],
+                    }),
+                    routing_header: SourceRoutingHeader {
+                        hop_index: 1,
+                        hops: vec![12, 11, 1],
+                    },
+                    session_id: id,
+                });
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        };
+
+        send_traffic(&mut drone, 0);
+        assert_eq!(drone.flood_first_seen.len(), 4);
+        assert_eq!(drone.seen_flood_ids.len(), 4);
+        assert_eq!(drone.session_fragment_totals.len(), 4);
+        assert_eq!(drone.session_order.len(), 4);
+        assert_eq!(drone.seen_flood_responses.len(), 4);
+
+        // 15 entries leave 2 per structure
+        drone.handle_control(DroneControl::SetStateBudget(15));
+        assert_eq!(drone.flood_first_seen.len(), 2);
+        assert_eq!(drone.seen_flood_ids.len(), 2);
+        assert!(drone.has_seen_flood(3, 1));
+        assert_eq!(drone.session_fragment_totals.len(), 2);
+        assert_eq!(drone.session_order.len(), 2);
+        assert_eq!(drone.seen_flood_responses.len(), 2);
+
+        send_traffic(&mut drone, 10);
+        assert_eq!(drone.flood_first_seen.len(), 2);
+        assert_eq!(drone.seen_flood_ids.len(), 2);
+        assert!(drone.has_seen_flood(13, 1));
+        assert_eq!(drone.session_fragment_totals.len(), 2);
+        assert_eq!(drone.session_order.len(), 2);
+        assert_eq!(drone.seen_flood_responses.len(), 2);
+    }
+
+    /// Tests that route validation accepts a valid route and reports each kind of invalid route.
+    #[test]
+    fn test_validate_route() {
+        let neighbors = HashSet::from([1, 12]);
+        let drone_ids = 11..=20;
+        let validate = |hop_index, hops: Vec<NodeId>| {
+            Drone::validate_route(
+                &SourceRoutingHeader { hop_index, hops },
+                &neighbors,
+                Some(&drone_ids),
+            )
+        };
+
+        assert_eq!(validate(1, vec![1, 11, 12, 21]), Ok(()));
+        assert_eq!(
+            validate(1, vec![11; MAX_ROUTE_LENGTH + 1]),
+            Err(RouteError::TooLong(MAX_ROUTE_LENGTH + 1))
+        );
+        assert_eq!(validate(0, vec![11, 12, 21]), Err(RouteError::HopIndexZero));
+        assert_eq!(
+            validate(4, vec![1, 11, 12, 21]),
+            Err(RouteError::HopIndexOutOfBounds {
+                hop_index: 4,
+                len: 4
+            })
+        );
+        assert_eq!(
+            validate(1, vec![1, 11, 12, 12, 21]),
+            Err(RouteError::SelfLoop(12))
+        );
+        assert_eq!(validate(1, vec![1, 11]), Err(RouteError::EndsAtDrone(11)));
+        assert_eq!(
+            validate(1, vec![1, 11, 13, 21]),
+            Err(RouteError::UnreachableNextHop(13))
+        );
+        assert_eq!(
+            validate(1, vec![1, 11, 12, 13]),
+            Err(RouteError::DestinationIsDrone(13))
+        );
+        // The destination check only applies with a drone ID range
+        assert_eq!(
+            Drone::validate_route(
+                &SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12, 13],
+                },
+                &neighbors,
+                None,
+            ),
+            Ok(())
+        );
+    }
+
+    /// Tests that NACKing a packet other than a fragment is logged, and refused when enabled.
+    #[test]
+    fn test_non_fragment_nack() {
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_recent_logs(10);
+        drone.capture_nacks();
+        // Misrouted: the hop at index 1 is 13, not this drone
+        let ack = Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 5 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 13, 12, 21],
+            },
+            session_id: 8,
+        };
+
+        drone.process_packet_once(ack.clone());
+        assert!(drone.recent_logs(10).contains(
+            &"Building NACK for Ack packet of session 8, which is not a fragment".to_string()
+        ));
+        let nack_types: Vec<NackType> = drone
+            .drain_generated_nacks()
+            .into_iter()
+            .map(|(nack_type, _)| nack_type)
+            .collect();
+        assert_eq!(nack_types, vec![NackType::UnexpectedRecipient(11)]);
+
+        drone.handle_control(DroneControl::SetRefuseNonFragmentNacks(true));
+        drone.process_packet_once(ack);
+        assert!(drone.drain_generated_nacks().is_empty());
+    }
+
+    /// Tests that dropped and undeliverable packets are copied to the dead-letter channel.
+    #[test]
+    fn test_dead_letter_channel() {
+        let (dead_letter_send, dead_letter_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone
+            .with_dead_letter_channel(dead_letter_send)
+            .with_warm_up(1);
+
+        let ack = Packet {
+            pack_type: PacketType::Ack(Ack { fragment_index: 2 }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![21, 11, 13, 1],
+            },
+            session_id: 4,
+        };
+        drone.process_packet_once(ack.clone());
+        assert_eq!(dead_letter_recv.try_recv(), Ok(ack));
+
+        let fragment = Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 5,
+        };
+        drone.process_packet_once(fragment.clone());
+        assert_eq!(dead_letter_recv.try_recv(), Ok(fragment));
+        assert!(dead_letter_recv.try_recv().is_err());
+    }
+
+    /// Tests that a flood whose initiator disagrees with its path trace is logged, and dropped when enabled.
+    #[test]
+    fn test_flood_initiator_check() {
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone
+            .with_notification_channel(notification_send)
+            .with_recent_logs(10);
+        let flood = |flood_id| Packet {
+            pack_type: PacketType::FloodRequest(FloodRequest {
+                flood_id,
+                initiator_id: 2,
+                path_trace: vec![(1, NodeType::Client)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 0,
+                hops: vec![],
+            },
+            session_id: 0,
+        };
+
+        drone.process_packet_once(flood(1));
+        assert!(next_recv.try_recv().is_ok());
+        assert_eq!(
+            drone.recent_logs(1),
+            vec!["Flood 1 declares initiator 2 but its path trace starts at Some(1)"]
+        );
+
+        drone.handle_control(DroneControl::SetFloodInitiatorCheck(
+            FloodInitiatorCheck::Drop,
+        ));
+        drone.process_packet_once(flood(2));
+        assert!(next_recv.try_recv().is_err());
+        assert_eq!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::PacketDropped {
+                session_id: 0,
+                fragment_index: 0,
+                reason: DropReason::FloodInitiatorMismatch,
+            })
+        );
+    }
+
+    /// Tests the flood response session ID produced by each strategy.
+    #[test]
+    fn test_flood_session_strategy() {
+        let rng = StdRng::seed_from_u64(3);
+        let mut expected_rng = rng.clone();
+
+        let TestDrone { mut drone, .. } = new_test_drone_with_rng(11, [], 0.0, rng);
+
+        assert_eq!(drone.flood_session_id(3), expected_rng.gen::<u64>());
+
+        drone.handle_control(DroneControl::SetFloodSessionStrategy(
+            FloodSessionStrategy::FloodId,
+        ));
+        assert_eq!(drone.flood_session_id(3), 3);
+
+        drone.handle_control(DroneControl::SetFloodSessionStrategy(
+            FloodSessionStrategy::Hash,
+        ));
+        assert_eq!(drone.flood_session_id(3), 17060497732321409724);
+        assert_eq!(drone.flood_session_id(3), 17060497732321409724);
+        assert_eq!(drone.flood_session_id(4), 18361567463430324293);
+        assert_eq!(flood::flood_session_hash(12, 3), 12436448812067284075);
+    }
+
+    /// Tests that a fragment is NACKed as unreachable once the retries on a full channel run out.
+    #[test]
+    fn test_forward_retry() {
+        let (next_send, next_recv) = crossbeam_channel::bounded(1);
+
+        let TestDrone {
+            drone,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+        let mut drone = drone.with_recent_logs(100);
+        drone.apply_command(DroneCommand::AddSender(12, next_send.clone()));
+        drone.handle_control(DroneControl::SetForwardRetry(3, Duration::from_millis(1)));
+
+        let fragment = |fragment_index| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index,
+                total_n_fragments: 2,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 9,
+        };
+
+        // The channel stays full for every retry
+        next_send.send(fragment(0)).unwrap();
+        drone.process_packet_once(fragment(1));
+
+        let retries = drone
+            .recent_logs(100)
+            .iter()
+            .filter(|line| line.starts_with("Channel full, retrying"))
+            .count();
+        assert_eq!(retries, 3);
+        assert_eq!(next_recv.len(), 1);
+        assert!(matches!(
+            client_recv.try_recv().map(|p| p.pack_type),
+            Ok(PacketType::Nack(Nack {
+                fragment_index: 1,
+                nack_type: NackType::ErrorInRouting(12),
+            }))
+        ));
+    }
+
+    /// Tests that a retried send succeeds as soon as the neighbor takes the packet.
+    #[test]
+    fn test_forward_retry_delivered() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv],
+            ..
+        } = new_test_drone(11, [1], 0.0);
+        // A zero-capacity channel only accepts a send while the neighbor is waiting on it
+        let (next_send, next_recv) = crossbeam_channel::bounded(0);
+        drone.apply_command(DroneCommand::AddSender(12, next_send));
+        drone.handle_control(DroneControl::SetForwardRetry(
+            u32::MAX,
+            Duration::from_millis(1),
+        ));
+
+        let neighbor = std::thread::spawn(move || next_recv.recv_timeout(Duration::from_secs(5)));
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 1,
+                total_n_fragments: 2,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 9,
+        });
+
+        let delivered = neighbor.join().unwrap().unwrap();
+        assert_eq!(packet_fragment_index(&delivered), 1);
+        assert!(client_recv.try_recv().is_err());
+    }
+
+    /// Tests that retry delays double up to their cap and stop before exceeding the total wait.
+    #[test]
+    fn test_forward_retry_delays() {
+        let delays: Vec<Duration> = forward_retry_delays(Duration::from_millis(20), 6).collect();
+        assert_eq!(
+            delays,
+            [20, 40, 80, 100, 100, 100].map(Duration::from_millis)
+        );
+
+        let delays: Vec<Duration> = forward_retry_delays(Duration::MAX, u32::MAX).collect();
+        assert_eq!(delays, vec![MAX_FORWARD_RETRY_DELAY; 10]);
+        assert_eq!(delays.iter().sum::<Duration>(), MAX_FORWARD_RETRY_WAIT);
+
+        assert_eq!(forward_retry_delays(Duration::from_millis(1), 0).count(), 0);
+    }
+
+    /// Tests that only the designated packet gets a decision trace, covering each decision taken.
+    #[test]
+    fn test_trace_packet() {
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+        let rng = StdRng::seed_from_u64(5);
+        let mut expected_rng = rng.clone();
+
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone_with_rng(11, [1, 12], 0.0, rng);
+        let mut drone = drone.with_notification_channel(notification_send);
+        drone.handle_control(DroneControl::TracePacket(Some((9, 1))));
+
+        let fragment = |fragment_index| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index,
+                total_n_fragments: 2,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 9,
+        };
+
+        drone.process_packet_once(fragment(0));
+        expected_rng.gen::<f32>();
+        assert!(notification_recv.try_recv().is_err());
+
+        drone.process_packet_once(fragment(1));
+        assert_eq!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::DecisionTrace {
+                session_id: 9,
+                fragment_index: 1,
+                steps: vec![
+                    "Routing verified at hop 1".to_string(),
+                    format!(
+                        "Drop roll {:.4} against 0 -> keep",
+                        expected_rng.gen::<f32>()
+                    ),
+                    "Forwarding fragment to 12".to_string(),
+                    "Forwarded to 12".to_string(),
+                ],
+            })
+        );
+    }
+
+    /// Tests that a flood response ending at the drone is shortcut once and not forwarded.
+    #[test]
+    fn test_flood_response_ending_at_drone() {
+        let TestDrone {
+            mut drone,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let response = Packet {
+            pack_type: PacketType::FloodResponse(FloodResponse {
+                flood_id: 6,
+                path_trace: vec![(11, NodeType::Drone), (12, NodeType::Drone)],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![12, 11],
+            },
+            session_id: 2,
+        };
+
+        let (_, events) = drone.process_packet_capturing_events(response.clone());
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            DroneEvent::ControllerShortcut(shortcut) if *shortcut == response
+        ));
+        assert!(client_recv.try_recv().is_err());
+        assert!(next_recv.try_recv().is_err());
+    }
+
+    /// Tests that a packet with the echo session ID returns along its reversed route.
+    #[test]
+    fn test_echo_session() {
+        let TestDrone {
+            mut drone,
+            neighbors: [previous_recv, next_recv],
+            ..
+        } = new_test_drone(11, [13, 12], 0.0);
+        drone.handle_control(DroneControl::SetEchoSession(Some(u64::MAX)));
+
+        let probe = |session_id| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 2,
+                hops: vec![1, 13, 11, 12, 21],
+            },
+            session_id,
+        };
+
+        drone.process_packet_once(probe(u64::MAX));
+        let echoed = previous_recv.try_recv().expect("Echo expected");
+        assert_eq!(
+            echoed.routing_header,
+            SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![11, 13, 1],
+            }
+        );
+        assert_eq!(echoed.pack_type, probe(u64::MAX).pack_type);
+        assert!(next_recv.try_recv().is_err());
+
+        drone.process_packet_once(probe(3));
+        assert!(next_recv.try_recv().is_ok());
+        assert!(previous_recv.try_recv().is_err());
+    }
+
+    /// Tests that the crash drain ends when a NACK would be routed back through the crashing drone.
+    #[test]
+    fn test_crash_drain_self_route() {
+        let (dead_letter_send, dead_letter_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            packets: packet_send_self,
+            neighbors: [next_recv],
+            ..
+        } = new_test_drone(11, [12], 0.0);
+        let mut drone = drone.with_dead_letter_channel(dead_letter_send);
+        // The drone is its own neighbor, so its NACK would land back in its own channel
+        drone.apply_command(DroneCommand::AddSender(11, packet_send_self.clone()));
+
+        packet_send_self
+            .send(Packet {
+                pack_type: PacketType::MsgFragment(Fragment {
+                    fragment_index: 0,
+                    total_n_fragments: 1,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![11, 11, 12, 21],
+                },
+                session_id: 1,
+            })
+            .unwrap();
+        drone.handle_command(Command::Drone(DroneCommand::Crash));
+
+        assert!(drone.should_exit);
+        assert_eq!(drone.inbound_queue_depth(), 0);
+        assert!(next_recv.try_recv().is_err());
+        let discarded = dead_letter_recv
+            .try_recv()
+            .expect("Discarded NACK expected");
+        assert!(matches!(
+            discarded.pack_type,
+            PacketType::Nack(Nack {
+                nack_type: NackType::ErrorInRouting(11),
+                ..
+            })
+        ));
+    }
+
+    /// Tests that forwarding the last fragment of a session sends a completion notification.
+    #[test]
+    fn test_session_completion_events() {
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_notification_channel(notification_send);
+        drone.handle_control(DroneControl::SetSessionCompletionEvents(true));
+
+        for fragment_index in 0..3 {
+            assert!(notification_recv.try_recv().is_err());
+            drone.process_packet_once(Packet {
+                pack_type: PacketType::MsgFragment(Fragment {
+                    fragment_index,
+                    total_n_fragments: 3,
+                    length: 128,
+                    data: [1; 128],
+                }),
+                routing_header: SourceRoutingHeader {
+                    hop_index: 1,
+                    hops: vec![1, 11, 12, 21],
+                },
+                session_id: 4,
+            });
+        }
+
+        assert_eq!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::SessionCompleted {
+                session_id: 4,
+                forwarded: 3,
+                total: 3,
+            })
+        );
+        assert!(drone.session_progress.is_empty());
+
+        // A malformed fragment with the largest index and no fragments neither overflows nor
+        // completes its session
+        drone.process_packet_once(Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: u64::MAX,
+                total_n_fragments: 0,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 21],
+            },
+            session_id: 5,
+        });
+        assert!(notification_recv.try_recv().is_err());
+    }
+
+    /// Tests that a route repeating a node in consecutive hops is flagged under each policy.
+    #[test]
+    fn test_duplicate_hop_policy() {
+        let (notification_send, notification_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            neighbors: [client_recv, next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_notification_channel(notification_send);
+        let fragment = Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: vec![1, 11, 12, 12, 21],
+            },
+            session_id: 3,
+        };
+
+        // Allowed by default
+        drone.process_packet_once(fragment.clone());
+        assert!(next_recv.try_recv().is_ok());
+
+        drone.handle_control(DroneControl::SetDuplicateHopPolicy(
+            DuplicateHopPolicy::Drop,
+        ));
+        drone.process_packet_once(fragment.clone());
+        assert!(next_recv.try_recv().is_err());
+        assert!(client_recv.try_recv().is_err());
+        assert_eq!(
+            notification_recv.try_recv(),
+            Ok(DroneNotification::PacketDropped {
+                session_id: 3,
+                fragment_index: 0,
+                reason: DropReason::DuplicateHop,
+            })
+        );
+
+        drone.handle_control(DroneControl::SetDuplicateHopPolicy(
+            DuplicateHopPolicy::Nack,
+        ));
+        drone.process_packet_once(fragment);
+        assert!(next_recv.try_recv().is_err());
+        let nack = client_recv.try_recv().expect("NACK expected");
+        assert!(matches!(
+            nack.pack_type,
+            PacketType::Nack(Nack {
+                nack_type: NackType::ErrorInRouting(12),
+                ..
+            })
+        ));
+    }
+
+    /// Tests that a PDR floor above the configured PDR raises the effective rate only.
+    #[test]
+    fn test_pdr_floor() {
+        let rng = StdRng::seed_from_u64(11);
+        let mut expected_rng = rng.clone();
+
+        let TestDrone { mut drone, .. } = new_test_drone_with_rng(11, [], 0.01, rng);
+        assert_eq!(drone.pdr_floor(), 0.0);
+        assert_eq!(drone.effective_pdr(), 0.01);
+
+        drone.handle_control(DroneControl::SetPdrFloor(0.3));
+        assert_eq!(drone.pdr_floor(), 0.3);
+        assert_eq!(drone.configured_pdr(), 0.01);
+        assert_eq!(drone.effective_pdr(), 0.3);
+        for _ in 0..100 {
+            let expected = expected_rng.gen::<f32>() < 0.3;
+            assert_eq!(drone.should_drop(PacketKind::Fragment), expected);
+        }
+
+        // A PDR above the floor takes over again
+        drone.apply_command(DroneCommand::SetPacketDropRate(0.5));
+        assert_eq!(drone.effective_pdr(), 0.5);
+
+        drone.handle_control(DroneControl::SetPdrFloor(1.5));
+        assert_eq!(drone.pdr_floor(), 0.3);
+    }
+
+    /// Tests that the events of one packet share a correlation ID, distinct from other packets.
+    #[test]
+    fn test_correlated_subscriber() {
+        let (correlated_send, correlated_recv) = crossbeam_channel::unbounded();
+
+        let TestDrone {
+            drone,
+            events: _controller_events,
+            neighbors: [_client_recv, _next_recv],
+            ..
+        } = new_test_drone(11, [1, 12], 0.0);
+        let mut drone = drone.with_warm_up(2);
+        drone.add_correlated_subscriber(correlated_send);
+        drone.handle_control(DroneControl::SetDestinationIsDronePolicy(
+            DestinationIsDronePolicy::ControllerShortcut,
+        ));
+
+        let fragment = |fragment_index, hops| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index,
+                total_n_fragments: 2,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader { hop_index: 1, hops },
+            session_id: 7,
+        };
+
+        // Dropped during warm-up, then shortcut once it ends at the drone
+        drone.process_packet_once(fragment(0, vec![1, 11, 12]));
+        drone.process_packet_once(fragment(1, vec![1, 11, 12]));
+        drone.process_packet_once(fragment(0, vec![1, 11]));
+
+        let events: Vec<CorrelatedEvent> = correlated_recv.try_iter().collect();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0].event, DroneEvent::PacketDropped(_)));
+        assert!(matches!(events[2].event, DroneEvent::ControllerShortcut(_)));
+        assert_eq!(events[0].correlation_id, events[2].correlation_id);
+        assert_ne!(events[0].correlation_id, events[1].correlation_id);
+    }
+
+    /// Tests that a fragment arriving on a different route than its session's previous one is logged.
+    #[test]
+    fn test_route_change_detection() {
+        let TestDrone {
+            drone,
+            neighbors: [_client_recv, _d12_recv, _d13_recv],
+            ..
+        } = new_test_drone(11, [1, 12, 13], 0.0);
+        let mut drone = drone.with_recent_logs(10);
+        drone.handle_control(DroneControl::SetRouteChangeDetection(true));
+
+        let fragment = |fragment_index, hops| Packet {
+            pack_type: PacketType::MsgFragment(Fragment {
+                fragment_index,
+                total_n_fragments: 3,
+                length: 128,
+                data: [1; 128],
+            }),
+            routing_header: SourceRoutingHeader { hop_index: 1, hops },
+            session_id: 5,
+        };
+        let route_changes = |drone: &Drone| {
+            drone
+                .recent_logs(10)
+                .into_iter()
+                .filter(|line| line.starts_with("Route of session"))
+                .collect::<Vec<String>>()
+        };
+
+        drone.process_packet_once(fragment(0, vec![1, 11, 12, 21]));
+        drone.process_packet_once(fragment(1, vec![1, 11, 12, 21]));
+        assert!(route_changes(&drone).is_empty());
+
+        drone.process_packet_once(fragment(2, vec![1, 11, 13, 21]));
+        assert_eq!(
+            route_changes(&drone),
+            vec!["Route of session 5 changed from [1, 11, 12, 21] to [1, 11, 13, 21]"]
+        );
     }
 }
@@ -0,0 +1,128 @@
+//! Arrival-ordered handling of the drone's input channels, used in fair ordering mode.
+//! Messages are moved into one queue as soon as the drone receives them, so they are handled in
+//! the order they reached the drone rather than in the order of the channels.
+
+use super::Drone;
+use crate::control::DroneControl;
+use crossbeam_channel::{never, Select, TryRecvError};
+use network_node::{Command, NetworkNode};
+use std::collections::VecDeque;
+use wg_2024::{controller::DroneCommand, packet::Packet};
+
+/// A message received on one of the drone's input channels.
+pub(super) enum Arrival {
+    Command(DroneCommand),
+    Control(DroneControl),
+    Packet(Packet),
+}
+
+impl Drone {
+    /// Moves every message ready on the input channels to the back of the arrival queue.
+    ///
+    /// Messages that became ready together (while the drone was busy) have no known relative
+    /// order, so they are interleaved across the channels.
+    ///
+    /// Only one packet is taken per round of the interleaving, and none while a packet is still
+    /// queued, so a flood of packets stays in the bounded packet channel and keeps its
+    /// backpressure. Packets are also left in their channel while the drone is paused.
+    pub(super) fn collect_arrivals(&mut self) {
+        let mut commands: VecDeque<Arrival> = self
+            .sim_contr_recv
+            .try_iter()
+            .map(Arrival::Command)
+            .collect();
+        let mut control = VecDeque::new();
+        loop {
+            match self.control_recv.try_recv() {
+                Ok(message) => control.push_back(Arrival::Control(message)),
+                Err(TryRecvError::Empty) => break,
+                // The control channel is optional: stop polling it once its sender is gone
+                Err(TryRecvError::Disconnected) => {
+                    self.control_recv = never();
+                    break;
+                }
+            }
+        }
+        let packet_queued = self
+            .arrivals
+            .iter()
+            .any(|arrival| matches!(arrival, Arrival::Packet(_)));
+        let mut packets: VecDeque<Arrival> = if self.paused || packet_queued {
+            VecDeque::new()
+        } else {
+            let rounds = commands.len().max(control.len()).max(1);
+            self.packet_recv
+                .try_iter()
+                .take(rounds)
+                .map(Arrival::Packet)
+                .collect()
+        };
+
+        while !(commands.is_empty() && control.is_empty() && packets.is_empty()) {
+            for source in [&mut commands, &mut control, &mut packets] {
+                if let Some(arrival) = source.pop_front() {
+                    self.arrivals.push_back(arrival);
+                }
+            }
+        }
+    }
+
+    /// Removes the oldest arrival that can be handled now: any arrival, or the oldest non-packet
+    /// one while the drone is paused.
+    pub(super) fn pop_arrival(&mut self) -> Option<Arrival> {
+        if !self.paused {
+            return self.arrivals.pop_front();
+        }
+        let position = self
+            .arrivals
+            .iter()
+            .position(|arrival| !matches!(arrival, Arrival::Packet(_)))?;
+        self.arrivals.remove(position)
+    }
+
+    /// Removes and returns every packet waiting in the arrival queue, oldest first.
+    pub(super) fn take_arrived_packets(&mut self) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        for arrival in std::mem::take(&mut self.arrivals) {
+            match arrival {
+                Arrival::Packet(packet) => packets.push(packet),
+                other => self.arrivals.push_back(other),
+            }
+        }
+        packets
+    }
+
+    /// Handles a single arrival.
+    pub(super) fn handle_arrival(&mut self, arrival: Arrival) {
+        match arrival {
+            Arrival::Command(drone_command) => {
+                self.handle_command(Command::Drone(drone_command));
+            }
+            Arrival::Control(control) => self.handle_control(control),
+            Arrival::Packet(packet) => self.should_exit = self.dispatch_packet(packet),
+        }
+    }
+
+    /// Handles the oldest arrival, blocking until a channel or timer is ready if there is none.
+    ///
+    /// Used by the event loop in fair ordering mode, so that messages are handled in the order
+    /// they were received regardless of their channel.
+    pub(super) fn handle_next_arrival(&mut self) {
+        self.collect_arrivals();
+        if let Some(arrival) = self.pop_arrival() {
+            self.handle_arrival(arrival);
+            return;
+        }
+
+        let packet_recv = self.data_plane();
+        let mut select = Select::new();
+        select.recv(&self.sim_contr_recv);
+        select.recv(&self.control_recv);
+        select.recv(&packet_recv);
+        select.recv(&self.deadline);
+        select.recv(&self.heartbeat);
+        select.recv(&self.log_boost);
+        select.recv(&self.flood_response_flush);
+        select.ready();
+    }
+}
@@ -0,0 +1,204 @@
+//! Drone configuration snapshot.
+//! Allows a replacement drone to inherit the settings of the one it replaces.
+
+use super::Drone;
+use crate::control::{
+    CrashDrainMode, CrashDrainOrder, DestinationIsDronePolicy, DuplicateHopPolicy, EventKind,
+    FloodFanOutPolicy, FloodInitiatorCheck, FloodSessionStrategy, FragmentTotalCheck,
+    HopIndexZeroPolicy, PacketKind, SessionRetention, ShortcutFailurePolicy,
+};
+use crossbeam_channel::{Receiver, Sender};
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+use wg_2024::{
+    controller::{DroneCommand, DroneEvent},
+    network::NodeId,
+    packet::Packet,
+};
+
+/// Drone-specific settings adjustable through control messages.
+#[derive(Debug, Clone)]
+pub(super) struct Settings {
+    pub(super) trace_session: Option<u64>,
+    pub(super) max_flood_depth: Option<usize>,
+    pub(super) flood_fan_out: Option<usize>,
+    /// Whether the fan-out cap was set explicitly, which disables the large neighbor set cap.
+    pub(super) flood_fan_out_configured: bool,
+    pub(super) flood_fan_out_policy: FloodFanOutPolicy,
+    pub(super) log_drop_decisions: bool,
+    pub(super) fragment_total_check: FragmentTotalCheck,
+    pub(super) flood_session_strategy: FloodSessionStrategy,
+    pub(super) fair_ordering: bool,
+    /// Drop rates of routed packets other than fragments, which use the PDR.
+    pub(super) drop_rates: HashMap<PacketKind, f32>,
+    pub(super) crash_drain_order: CrashDrainOrder,
+    pub(super) crash_drain_mode: CrashDrainMode,
+    pub(super) restart_cooldown: Duration,
+    pub(super) flood_validity: Option<Duration>,
+    pub(super) max_tracked_floods: Option<usize>,
+    /// Cap on the seen flood set, only set through the state budget.
+    pub(super) max_seen_floods: Option<usize>,
+    pub(super) flood_timestamps: bool,
+    pub(super) neighbor_activity: bool,
+    pub(super) mirror_nacks: bool,
+    pub(super) drone_id_range: Option<RangeInclusive<NodeId>>,
+    pub(super) answer_floods_while_crashing: bool,
+    pub(super) refuse_non_fragment_nacks: bool,
+    pub(super) flood_initiator_check: FloodInitiatorCheck,
+    pub(super) forward_retries: u32,
+    pub(super) forward_retry_delay: Duration,
+    pub(super) traced_packet: Option<(u64, u64)>,
+    pub(super) echo_session: Option<u64>,
+    pub(super) session_completion: bool,
+    pub(super) duplicate_hop_policy: DuplicateHopPolicy,
+    pub(super) pdr_floor: f32,
+    pub(super) route_change_detection: bool,
+    /// Maximum number of low-priority events sent per second.
+    pub(super) event_rate_limit: Option<u32>,
+    pub(super) disabled_events: HashSet<EventKind>,
+    /// Number of flood responses remembered for deduplication, `None` to forward them all.
+    pub(super) flood_response_dedup: Option<usize>,
+    pub(super) announce_crash: bool,
+    /// Per-session buffer size for in-order forwarding, `None` when fragments pass immediately.
+    pub(super) fragment_ordering: Option<usize>,
+    pub(super) destination_is_drone_policy: DestinationIsDronePolicy,
+    pub(super) hop_index_zero_policy: HopIndexZeroPolicy,
+    pub(super) shortcut_failure_policy: ShortcutFailurePolicy,
+    pub(super) session_retention: SessionRetention,
+    /// Counts fragment drop decisions without dropping.
+    pub(super) measure_only: bool,
+    /// Window during which built flood responses are held back, `None` to send them immediately.
+    pub(super) flood_response_batching: Option<Duration>,
+    /// Weighted neighbors that may replace a given next hop.
+    #[cfg(feature = "multipath")]
+    pub(super) multipath_groups: HashMap<NodeId, Vec<(NodeId, u32)>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            trace_session: None,
+            max_flood_depth: None,
+            flood_fan_out: None,
+            flood_fan_out_configured: false,
+            flood_fan_out_policy: FloodFanOutPolicy::LowestIdFirst,
+            log_drop_decisions: false,
+            fragment_total_check: FragmentTotalCheck::Off,
+            flood_session_strategy: FloodSessionStrategy::Random,
+            fair_ordering: false,
+            drop_rates: HashMap::new(),
+            crash_drain_order: CrashDrainOrder::Fifo,
+            crash_drain_mode: CrashDrainMode::Nack,
+            restart_cooldown: Duration::ZERO,
+            flood_validity: None,
+            max_tracked_floods: None,
+            max_seen_floods: None,
+            flood_timestamps: false,
+            neighbor_activity: false,
+            mirror_nacks: false,
+            drone_id_range: None,
+            answer_floods_while_crashing: true,
+            refuse_non_fragment_nacks: false,
+            flood_initiator_check: FloodInitiatorCheck::Log,
+            forward_retries: 0,
+            forward_retry_delay: Duration::ZERO,
+            traced_packet: None,
+            echo_session: None,
+            session_completion: false,
+            duplicate_hop_policy: DuplicateHopPolicy::Allow,
+            pdr_floor: 0.0,
+            route_change_detection: false,
+            event_rate_limit: None,
+            disabled_events: HashSet::new(),
+            flood_response_dedup: None,
+            announce_crash: true,
+            fragment_ordering: None,
+            destination_is_drone_policy: DestinationIsDronePolicy::Nack,
+            hop_index_zero_policy: HopIndexZeroPolicy::Drop,
+            shortcut_failure_policy: ShortcutFailurePolicy::Drop,
+            session_retention: SessionRetention::Unbounded,
+            measure_only: false,
+            flood_response_batching: None,
+            #[cfg(feature = "multipath")]
+            multipath_groups: HashMap::new(),
+        }
+    }
+}
+
+/// Configuration of a drone: its ID, PDR, drone-specific settings and the options set with the
+/// `with_*` builders, except for channels and callbacks.
+///
+/// Runtime state such as seen flood IDs, generated NACKs and the random generator state is
+/// not part of the configuration, so a drone built from it starts fresh, including a full warm-up.
+/// The exception is the crash time, which starts the restart cooldown of the replacement.
+#[derive(Debug, Clone)]
+pub struct DroneConfig {
+    pub id: NodeId,
+    pub pdr: f32,
+    settings: Settings,
+    crashed_at: Option<Instant>,
+    max_lifetime: Option<Duration>,
+    heartbeat_interval: Option<Duration>,
+    warm_up: usize,
+    recent_logs_capacity: Option<usize>,
+}
+
+impl DroneConfig {
+    /// Builds a new drone with this configuration on the given channels.
+    ///
+    /// If the snapshot was taken from a crashed drone, the new drone rejects controller commands
+    /// until the restart cooldown has elapsed since the crash.
+    pub fn build(
+        &self,
+        controller_send: Sender<DroneEvent>,
+        controller_recv: Receiver<DroneCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+    ) -> Drone {
+        let mut drone = Drone::new_with_rng(
+            self.id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            self.pdr,
+            StdRng::from_entropy(),
+        );
+        drone.settings = self.settings.clone();
+        drone.cap_large_flood_fan_out();
+        drone.previous_crash_at = self.crashed_at;
+        drone.max_lifetime = self.max_lifetime;
+        drone.heartbeat_interval = self.heartbeat_interval;
+        drone = drone.with_warm_up(self.warm_up);
+        if let Some(capacity) = self.recent_logs_capacity {
+            drone = drone.with_recent_logs(capacity);
+        }
+        if let Some(rate) = self.settings.event_rate_limit {
+            drone.event_bucket.fill(rate);
+        }
+        drone
+    }
+}
+
+impl Drone {
+    /// Returns a snapshot of the drone's configuration, e.g. to spawn a replacement after a crash.
+    pub fn clone_config(&self) -> DroneConfig {
+        let mut settings = self.settings.clone();
+        // The automatic fan-out cap depends on the neighbors, which are not part of the configuration
+        if !settings.flood_fan_out_configured {
+            settings.flood_fan_out = None;
+        }
+        DroneConfig {
+            id: self.id,
+            pdr: self.pdr,
+            settings,
+            crashed_at: self.crashed_at,
+            max_lifetime: self.max_lifetime,
+            heartbeat_interval: self.heartbeat_interval,
+            warm_up: self.warm_up,
+            recent_logs_capacity: self.recent_logs_capacity(),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Correlation IDs for events, for joining the events of one packet across drones.
+//! The controller's `DroneEvent` has no room for an ID, so correlated events come in an envelope.
+
+use super::decision_trace::packet_fragment_index;
+use super::{mix64, Drone};
+use crossbeam_channel::Sender;
+use wg_2024::{controller::DroneEvent, packet::Packet};
+
+/// An event together with the correlation ID of its packet.
+#[derive(Debug, Clone)]
+pub struct CorrelatedEvent {
+    /// Derived from the packet's session ID and fragment index only, so every drone computes the
+    /// same ID for the same packet.
+    pub correlation_id: u64,
+    /// The event as sent to the controller.
+    pub event: DroneEvent,
+}
+
+/// Returns the correlation ID of a packet.
+pub(super) fn correlation_id(packet: &Packet) -> u64 {
+    mix64(packet.session_id ^ mix64(packet_fragment_index(packet)))
+}
+
+/// Returns the packet carried by an event.
+fn event_packet(event: &DroneEvent) -> &Packet {
+    match event {
+        DroneEvent::PacketSent(packet)
+        | DroneEvent::PacketDropped(packet)
+        | DroneEvent::ControllerShortcut(packet) => packet,
+    }
+}
+
+impl Drone {
+    /// Registers a channel receiving a copy of every event sent by the drone itself, wrapped with
+    /// the correlation ID of its packet.
+    ///
+    /// Like [`Drone::add_event_subscriber`], events emitted by the common `NetworkNode` handling are
+    /// not included, and subscribers whose receiving side is gone are removed.
+    pub fn add_correlated_subscriber(&mut self, subscriber: Sender<CorrelatedEvent>) {
+        self.correlated_subscribers.push(subscriber);
+    }
+
+    /// Sends a correlated copy of an event to every correlated subscriber.
+    pub(super) fn send_correlated(&mut self, event: &DroneEvent) {
+        if self.correlated_subscribers.is_empty() {
+            return;
+        }
+        let correlated = CorrelatedEvent {
+            correlation_id: correlation_id(event_packet(event)),
+            event: event.clone(),
+        };
+        self.correlated_subscribers
+            .retain(|subscriber| subscriber.send(correlated.clone()).is_ok());
+    }
+}
@@ -0,0 +1,58 @@
+//! Step-by-step decision trace for a single designated packet.
+//! Lets a controller debug one packet without reading the drone's whole log.
+
+use super::Drone;
+use crate::control::DroneNotification;
+use std::fmt;
+use wg_2024::packet::{Packet, PacketType};
+
+/// Returns the fragment index carried by a packet, or 0 for flood packets.
+pub(super) fn packet_fragment_index(packet: &Packet) -> u64 {
+    match &packet.pack_type {
+        PacketType::MsgFragment(fragment) => fragment.fragment_index,
+        PacketType::Ack(ack) => ack.fragment_index,
+        PacketType::Nack(nack) => nack.fragment_index,
+        PacketType::FloodRequest(_) | PacketType::FloodResponse(_) => 0,
+    }
+}
+
+impl Drone {
+    /// Starts collecting decisions if the packet is the one designated with
+    /// [`DroneControl::TracePacket`](crate::DroneControl::TracePacket).
+    ///
+    /// Returns `true` if a trace was started.
+    pub(super) fn start_decision_trace(&self, packet: &Packet) -> bool {
+        let traced =
+            self.settings.traced_packet == Some((packet.session_id, packet_fragment_index(packet)));
+        if traced {
+            *self.decision_trace.borrow_mut() = Some(Vec::new());
+        }
+        traced
+    }
+
+    /// Records a decision taken while handling the designated packet; a no-op for any other packet.
+    pub(super) fn record_decision(&self, decision: fmt::Arguments) {
+        if let Some(steps) = self.decision_trace.borrow_mut().as_mut() {
+            steps.push(decision.to_string());
+        }
+    }
+
+    /// Ends the current decision trace, logging it as one block and sending it as a notification.
+    pub(super) fn finish_decision_trace(&self, session_id: u64, fragment_index: u64) {
+        let Some(steps) = self.decision_trace.borrow_mut().take() else {
+            return;
+        };
+        drone_status!(
+            self,
+            "Decision trace for fragment {} of session {}:\n  {}",
+            fragment_index,
+            session_id,
+            steps.join("\n  ")
+        );
+        self.notify(DroneNotification::DecisionTrace {
+            session_id,
+            fragment_index,
+            steps,
+        });
+    }
+}
@@ -0,0 +1,316 @@
+//! Flood handling policies implemented by the drone itself.
+//! Unrestricted floods are handled by the common `NetworkNode` logic.
+
+use super::{mix64, Drone};
+use crate::control::{DropReason, FloodFanOutPolicy, FloodInitiatorCheck, FloodSessionStrategy};
+use crossbeam_channel::{at, never};
+use network_node::NetworkNode;
+use rand::{seq::SliceRandom, Rng};
+use std::time::{Duration, Instant};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{FloodRequest, FloodResponse, NodeType, Packet, PacketType},
+};
+
+/// Returns the key identifying a flood in the seen flood set, matching the common `NetworkNode` handling.
+pub(super) fn flood_key(flood_id: u64, initiator_id: NodeId) -> String {
+    format!("{}_{}", flood_id, initiator_id)
+}
+
+/// Mixes a node ID and a flood ID into a session ID.
+pub(super) fn flood_session_hash(node_id: NodeId, flood_id: u64) -> u64 {
+    mix64(flood_id ^ (u64::from(node_id) << 56))
+}
+
+impl Drone {
+    /// Returns `true` if the drone has already processed the flood started by `initiator_id`
+    /// with the given ID.
+    pub fn has_seen_flood(&self, flood_id: u64, initiator_id: NodeId) -> bool {
+        self.seen_flood_ids
+            .contains(&flood_key(flood_id, initiator_id))
+    }
+
+    /// Compares the flood request's initiator with the first node of its path trace.
+    ///
+    /// Returns `false` if the request must be discarded.
+    pub(super) fn check_flood_initiator(
+        &mut self,
+        packet: &Packet,
+        flood_request: &FloodRequest,
+    ) -> bool {
+        if self.settings.flood_initiator_check == FloodInitiatorCheck::Off {
+            return true;
+        }
+        let first_node = flood_request
+            .path_trace
+            .first()
+            .map(|(node_id, _)| *node_id);
+        if first_node == Some(flood_request.initiator_id) {
+            return true;
+        }
+
+        drone_error!(
+            self,
+            "Flood {} declares initiator {} but its path trace starts at {:?}",
+            flood_request.flood_id,
+            flood_request.initiator_id,
+            first_node
+        );
+        if self.settings.flood_initiator_check == FloodInitiatorCheck::Drop {
+            self.record_drop(packet, DropReason::FloodInitiatorMismatch);
+            return false;
+        }
+        true
+    }
+
+    /// Returns `true` if forwarding the flood request would exceed the configured maximum depth.
+    ///
+    /// The depth is the length of the path trace once this drone has been appended to it.
+    pub(super) fn flood_depth_reached(&self, flood_request: &FloodRequest) -> bool {
+        self.settings
+            .max_flood_depth
+            .is_some_and(|max_depth| flood_request.path_trace.len() + 1 >= max_depth)
+    }
+
+    /// Returns the tracked floods, as `flood_id_initiator_id` keys, with the time each was first
+    /// seen, oldest first.
+    ///
+    /// Floods are only tracked while [`DroneControl::SetFloodTimestamps`](crate::DroneControl::SetFloodTimestamps)
+    /// or [`DroneControl::SetFloodValidity`](crate::DroneControl::SetFloodValidity) is set, so an
+    /// entry missing here may still be in the seen set used for deduplication.
+    pub fn seen_flood_times(&self) -> Vec<(String, Instant)> {
+        let mut seen: Vec<(String, Instant)> = self
+            .flood_first_seen
+            .iter()
+            .map(|(flood_key, first_seen)| (flood_key.clone(), *first_seen))
+            .collect();
+        seen.sort_by_key(|(_, first_seen)| *first_seen);
+        seen
+    }
+
+    /// Returns the time the flood was first seen, starting to track it if it is new.
+    ///
+    /// Tracking a new flood beyond the configured cap evicts the oldest tracked one.
+    pub(super) fn track_flood(&mut self, flood_key: String) -> Instant {
+        if let Some(first_seen) = self.flood_first_seen.get(&flood_key) {
+            return *first_seen;
+        }
+
+        let now = Instant::now();
+        self.flood_first_seen.insert(flood_key, now);
+        self.evict_tracked_floods();
+        now
+    }
+
+    /// Stops tracking the oldest floods until the tracked floods fit the configured cap.
+    pub(super) fn evict_tracked_floods(&mut self) {
+        let Some(max_tracked) = self.settings.max_tracked_floods else {
+            return;
+        };
+        while self.flood_first_seen.len() > max_tracked {
+            let Some(oldest) = self
+                .flood_first_seen
+                .iter()
+                .min_by_key(|(_, first_seen)| **first_seen)
+                .map(|(flood_key, _)| flood_key.clone())
+            else {
+                return;
+            };
+            drone_status!(self, "No longer tracking flood {}", oldest);
+            self.flood_first_seen.remove(&oldest);
+        }
+    }
+
+    /// Remembers when a flood is added to the seen set, forgetting the oldest seen floods beyond
+    /// the cap set by the state budget.
+    pub(super) fn remember_seen_flood(&mut self, flood_key: String) {
+        self.seen_flood_order.push_back(flood_key);
+        self.evict_seen_floods();
+    }
+
+    /// Forgets the oldest seen floods until the seen set fits the cap set by the state budget.
+    /// A forgotten flood counts as new if one of its requests arrives again.
+    pub(super) fn evict_seen_floods(&mut self) {
+        let Some(max_seen) = self.settings.max_seen_floods else {
+            return;
+        };
+        while self.seen_flood_order.len() > max_seen {
+            let Some(oldest) = self.seen_flood_order.pop_front() else {
+                return;
+            };
+            self.seen_flood_ids.remove(&oldest);
+        }
+    }
+
+    /// Returns `true` if a response from the same responder to the same flood was already seen,
+    /// when flood response deduplication is enabled. Otherwise remembers the response, forgetting
+    /// the oldest one beyond the capacity.
+    ///
+    /// Flood IDs are only unique per initiator, so a flood is identified by its ID together with
+    /// the first node of the path trace.
+    pub(super) fn is_duplicate_flood_response(&mut self, flood_response: &FloodResponse) -> bool {
+        let Some(capacity) = self.settings.flood_response_dedup else {
+            return false;
+        };
+        let (Some((initiator, _)), Some((responder, _))) = (
+            flood_response.path_trace.first(),
+            flood_response.path_trace.last(),
+        ) else {
+            return false;
+        };
+
+        let key = (flood_response.flood_id, *initiator, *responder);
+        if self.seen_flood_responses.contains(&key) {
+            return true;
+        }
+        self.seen_flood_responses.push_back(key);
+        while self.seen_flood_responses.len() > capacity {
+            self.seen_flood_responses.pop_front();
+        }
+        false
+    }
+
+    /// Answers a flood request with a flood response instead of forwarding it further.
+    ///
+    /// The response session ID follows the [`FloodSessionStrategy`].
+    /// With flood response batching, the response is held back until the batch is flushed.
+    pub(super) fn respond_to_flood(&mut self, mut flood_request: FloodRequest) {
+        flood_request.path_trace.push((self.id, NodeType::Drone));
+
+        // Route back along the path trace, starting at 1 since first hop is current node
+        let mut hops: Vec<NodeId> = flood_request
+            .path_trace
+            .iter()
+            .map(|(node_id, _)| *node_id)
+            .collect();
+        hops.reverse();
+
+        let session_id = self.flood_session_id(flood_request.flood_id);
+        let response = Packet {
+            pack_type: PacketType::FloodResponse(FloodResponse {
+                flood_id: flood_request.flood_id,
+                path_trace: flood_request.path_trace,
+            }),
+            routing_header: SourceRoutingHeader { hop_index: 1, hops },
+            session_id,
+        };
+
+        match self.settings.flood_response_batching {
+            Some(window) => self.batch_flood_response(response, window),
+            None => self.forward_routed(response),
+        }
+    }
+
+    /// Returns the session ID of a response to the given flood, following the flood session strategy.
+    pub(super) fn flood_session_id(&mut self, flood_id: u64) -> u64 {
+        match self.settings.flood_session_strategy {
+            FloodSessionStrategy::Random => self.random_generator.gen(),
+            FloodSessionStrategy::FloodId => flood_id,
+            FloodSessionStrategy::Hash => flood_session_hash(self.id, flood_id),
+        }
+    }
+
+    /// Holds a flood response until the current batch is flushed, starting a batch if none is open.
+    ///
+    /// A response identical to a held one apart from its session ID is dropped. A window too long
+    /// to represent never closes on its own.
+    fn batch_flood_response(&mut self, response: Packet, window: Duration) {
+        let duplicate = self.pending_flood_responses.iter().any(|pending| {
+            pending.pack_type == response.pack_type
+                && pending.routing_header == response.routing_header
+        });
+        if duplicate {
+            drone_status!(self, "Coalesced duplicate flood response");
+            return;
+        }
+
+        if self.pending_flood_responses.is_empty() {
+            self.flood_response_flush = Instant::now().checked_add(window).map_or_else(never, at);
+        }
+        self.pending_flood_responses.push(response);
+    }
+
+    /// Sends every held flood response and closes the current batch.
+    pub(super) fn flush_flood_responses(&mut self) {
+        self.flood_response_flush = never();
+        let responses = std::mem::take(&mut self.pending_flood_responses);
+        if responses.is_empty() {
+            return;
+        }
+
+        drone_status!(self, "Flushing {} flood responses", responses.len());
+        for response in responses {
+            self.forward_routed(response);
+        }
+    }
+
+    /// Forwards a flood request to at most `flood_fan_out` neighbors, chosen by the fan-out policy.
+    /// Without a cap, the request is forwarded to every neighbor but the previous hop. The previous
+    /// hop, the last node of the path trace, is never a target, with or without a cap.
+    ///
+    /// Duplicate requests and requests with nowhere to go are answered with a flood response.
+    pub(super) fn forward_flood_capped(
+        &mut self,
+        flood_request: FloodRequest,
+        routing_header: SourceRoutingHeader,
+        session_id: u64,
+    ) {
+        let flood_key = flood_key(flood_request.flood_id, flood_request.initiator_id);
+        if !self.seen_flood_ids.insert(flood_key) {
+            self.respond_to_flood(flood_request);
+            return;
+        }
+
+        // Deduplication only stops a flood on its second visit, so exclude the sender explicitly
+        // to avoid bouncing the request straight back within the same round
+        let previous_hop = flood_request.path_trace.last().map(|(node_id, _)| *node_id);
+        let candidates: Vec<NodeId> = self
+            .neighbors()
+            .into_iter()
+            .filter(|neighbor| Some(*neighbor) != previous_hop)
+            .collect();
+        if candidates.is_empty() {
+            self.respond_to_flood(flood_request);
+            return;
+        }
+
+        let targets = self.select_flood_targets(candidates);
+        drone_status!(
+            self,
+            "Forwarding flood {} to {:?}",
+            flood_request.flood_id,
+            targets
+        );
+
+        let mut flood_request = flood_request;
+        flood_request.path_trace.push((self.id, NodeType::Drone));
+        for neighbor in targets {
+            let packet = Packet {
+                pack_type: PacketType::FloodRequest(flood_request.clone()),
+                routing_header: routing_header.clone(),
+                session_id,
+            };
+            self.forward_to_neighbor(neighbor, packet);
+        }
+    }
+
+    /// Picks the flood targets among `candidates` (sorted by ID) according to the fan-out policy.
+    fn select_flood_targets(&mut self, mut candidates: Vec<NodeId>) -> Vec<NodeId> {
+        let fan_out = self.settings.flood_fan_out.unwrap_or(candidates.len());
+        if fan_out >= candidates.len() {
+            return candidates;
+        }
+
+        match self.settings.flood_fan_out_policy {
+            FloodFanOutPolicy::LowestIdFirst => {}
+            FloodFanOutPolicy::Random => candidates.shuffle(&mut self.random_generator),
+            FloodFanOutPolicy::RoundRobin => {
+                let offset = self.flood_rounds % candidates.len();
+                candidates.rotate_left(offset);
+                self.flood_rounds = self.flood_rounds.wrapping_add(1);
+            }
+        }
+        candidates.truncate(fan_out);
+        candidates
+    }
+}
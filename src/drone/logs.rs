@@ -0,0 +1,69 @@
+//! Bounded buffer of the drone's most recent log lines.
+//! Lets a controller show a node's recent activity without tailing a log file.
+
+use super::Drone;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Ring buffer holding at most `capacity` log lines, oldest first.
+#[derive(Debug)]
+pub(super) struct RecentLogs {
+    capacity: usize,
+    lines: VecDeque<String>,
+}
+
+impl RecentLogs {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, message: fmt::Arguments) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(message.to_string());
+    }
+}
+
+impl Drone {
+    /// Keeps the last `capacity` log lines of the drone in memory, see [`Drone::recent_logs`].
+    ///
+    /// Disabled by default, since every log line is then also formatted into the buffer.
+    pub fn with_recent_logs(mut self, capacity: usize) -> Self {
+        self.recent_logs = Some(RefCell::new(RecentLogs::new(capacity)));
+        self
+    }
+
+    /// Returns the capacity of the recent log buffer, or `None` if it is not enabled.
+    pub(super) fn recent_logs_capacity(&self) -> Option<usize> {
+        self.recent_logs
+            .as_ref()
+            .map(|recent_logs| recent_logs.borrow().capacity)
+    }
+
+    /// Returns up to `n` of the most recent log lines, oldest first.
+    ///
+    /// Returns an empty vector if the buffer was not enabled with [`Drone::with_recent_logs`].
+    pub fn recent_logs(&self, n: usize) -> Vec<String> {
+        let Some(recent_logs) = &self.recent_logs else {
+            return Vec::new();
+        };
+        let recent_logs = recent_logs.borrow();
+        let skip = recent_logs.lines.len().saturating_sub(n);
+        recent_logs.lines.iter().skip(skip).cloned().collect()
+    }
+
+    /// Appends a log line to the recent log buffer, if enabled.
+    pub(super) fn record_log(&self, message: fmt::Arguments) {
+        if let Some(recent_logs) = &self.recent_logs {
+            recent_logs.borrow_mut().push(message);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Experimental multipath forwarding, enabled by the `multipath` feature.
+//! Source routing normally dictates the next hop; here the drone may swap it for an
+//! equivalent neighbor to prototype load balancing. The rest of the route is kept as is,
+//! so every alternative must be able to continue it.
+
+use super::Drone;
+use rand::distributions::{Distribution, WeightedIndex};
+use wg_2024::{network::NodeId, packet::Packet};
+
+impl Drone {
+    /// Replaces the multipath group of `next_hop`, or removes it if `weights` is empty.
+    pub(super) fn set_multipath_group(&mut self, next_hop: NodeId, weights: Vec<(NodeId, u32)>) {
+        if weights.is_empty() {
+            drone_status!(self, "Multipath group for {} removed", next_hop);
+            self.settings.multipath_groups.remove(&next_hop);
+        } else {
+            drone_status!(
+                self,
+                "Multipath group for {} set to {:?}",
+                next_hop,
+                weights
+            );
+            self.settings.multipath_groups.insert(next_hop, weights);
+        }
+    }
+
+    /// Picks the next hop of a packet about to be forwarded among the multipath group of the
+    /// hop in its routing header, rewriting the header in place.
+    ///
+    /// Alternatives that are not usable neighbors are ignored; if no alternative has a positive
+    /// weight the header is left untouched.
+    pub(super) fn choose_multipath_hop(&mut self, packet: &mut Packet) {
+        let hop_index = packet.routing_header.hop_index;
+        let next_hop = packet.routing_header.hops[hop_index];
+        let Some(group) = self.settings.multipath_groups.get(&next_hop) else {
+            return;
+        };
+
+        let candidates: Vec<(NodeId, u32)> = group
+            .iter()
+            .copied()
+            .filter(|(neighbor, _)| {
+                self.packet_send.contains_key(neighbor)
+                    && !self.blackholed_neighbors.contains(neighbor)
+            })
+            .collect();
+        let Ok(distribution) = WeightedIndex::new(candidates.iter().map(|(_, weight)| *weight))
+        else {
+            return;
+        };
+
+        let chosen = candidates[distribution.sample(&mut self.random_generator)].0;
+        packet.routing_header.hops[hop_index] = chosen;
+    }
+}
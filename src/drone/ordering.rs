@@ -0,0 +1,128 @@
+//! In-order forwarding of fragments within a session.
+//! Fragments arriving ahead of their predecessors are held back in a bounded per-session buffer.
+
+use super::{retain_sessions, Drone};
+use crate::control::{DropReason, SessionRetention};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+use wg_2024::packet::{Packet, PacketType};
+
+/// Forwarding progress of a single session.
+#[derive(Debug, Default)]
+pub(super) struct SessionOrder {
+    /// Index of the next fragment allowed through.
+    next_index: u64,
+    /// Fragments waiting for their predecessors, by fragment index.
+    buffered: BTreeMap<u64, Packet>,
+}
+
+impl Drone {
+    /// Forwards a fragment once all fragments of its session with a lower index have passed.
+    ///
+    /// Sessions are expected to start at fragment 0. Fragments below the next expected index
+    /// (e.g. retransmissions) are forwarded immediately. A fragment that does not fit in the
+    /// session's buffer is dropped with a NACK.
+    ///
+    /// A session is forgotten once its last fragment has passed, or when evicted under the
+    /// [`SessionRetention`] policy; a fragment of a forgotten session starts it over.
+    pub(super) fn forward_in_order(&mut self, packet: Packet, capacity: usize) {
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            return;
+        };
+        let fragment_index = fragment.fragment_index;
+        let total_n_fragments = fragment.total_n_fragments;
+        let session_id = packet.session_id;
+        self.session_order
+            .entry(session_id)
+            .or_insert_with(|| (SessionOrder::default(), Instant::now()))
+            .1 = Instant::now();
+        self.evict_sessions();
+        let (session, _) = self
+            .session_order
+            .entry(session_id)
+            .or_insert_with(|| (SessionOrder::default(), Instant::now()));
+
+        if fragment_index < session.next_index {
+            self.forward_fragment(packet);
+            return;
+        }
+
+        if fragment_index > session.next_index {
+            let next_index = session.next_index;
+            if session.buffered.len() >= capacity && !session.buffered.contains_key(&fragment_index)
+            {
+                self.trace_packet(&packet, format_args!("Reorder buffer full"));
+                self.drop_fragment(packet, DropReason::ReorderBufferFull);
+                return;
+            }
+
+            self.trace_packet(
+                &packet,
+                format_args!(
+                    "Holding fragment {} until fragment {} passes",
+                    fragment_index, next_index
+                ),
+            );
+            if let Some((session, _)) = self.session_order.get_mut(&session_id) {
+                session.buffered.insert(fragment_index, packet);
+            }
+            return;
+        }
+
+        // The expected fragment: release it along with every buffered successor
+        let mut next_index = fragment_index + 1;
+        let mut ready = vec![packet];
+        while let Some(successor) = session.buffered.remove(&next_index) {
+            ready.push(successor);
+            next_index += 1;
+        }
+        session.next_index = next_index;
+        if next_index >= total_n_fragments && session.buffered.is_empty() {
+            self.session_order.remove(&session_id);
+        }
+
+        for packet in ready {
+            self.forward_fragment(packet);
+        }
+    }
+
+    /// Forwards every held-back fragment regardless of order, e.g. when in-order forwarding is
+    /// turned off.
+    pub(super) fn release_held_fragments(&mut self) {
+        let sessions = std::mem::take(&mut self.session_order);
+        for (_, (session, _)) in sessions {
+            for (_, packet) in session.buffered {
+                self.forward_fragment(packet);
+            }
+        }
+    }
+
+    /// Evicts the sessions that fall outside the session retention policy, forwarding the
+    /// fragments they still hold back regardless of order.
+    pub(super) fn evict_session_order(&mut self) {
+        let retention = self.settings.session_retention;
+        if retention == SessionRetention::Unbounded || self.session_order.is_empty() {
+            return;
+        }
+
+        let mut retained: HashMap<u64, ((), Instant)> = self
+            .session_order
+            .iter()
+            .map(|(session_id, (_, last_seen))| (*session_id, ((), *last_seen)))
+            .collect();
+        retain_sessions(&mut retained, retention);
+        let evicted: Vec<u64> = self
+            .session_order
+            .keys()
+            .filter(|session_id| !retained.contains_key(session_id))
+            .copied()
+            .collect();
+        for session_id in evicted {
+            if let Some((session, _)) = self.session_order.remove(&session_id) {
+                for (_, packet) in session.buffered {
+                    self.forward_fragment(packet);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Outbound event filtering and rate limiting.
+//! Protects the controller from event storms by shedding unwanted events and low-priority events
+//! beyond a set rate.
+
+use super::Drone;
+use crate::control::EventKind;
+use std::time::Instant;
+use wg_2024::controller::DroneEvent;
+
+/// Token bucket refilled continuously at `rate` tokens per second, holding at most one second of tokens.
+#[derive(Debug, Clone)]
+pub(super) struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub(super) fn new() -> Self {
+        Self {
+            tokens: 0.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes one token if available at the given rate, returning `false` otherwise.
+    fn try_take(&mut self, rate: u32) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        self.tokens = (self.tokens + elapsed * f64::from(rate)).min(f64::from(rate));
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Fills the bucket, so a newly set limit starts with a full second of budget.
+    pub(super) fn fill(&mut self, rate: u32) {
+        self.tokens = f64::from(rate);
+        self.last_refill = Instant::now();
+    }
+}
+
+/// Returns `true` for events that must reach the controller regardless of the rate limit.
+///
+/// Controller shortcuts carry packets that would otherwise be lost.
+fn is_high_priority(event: &DroneEvent) -> bool {
+    matches!(event, DroneEvent::ControllerShortcut(_))
+}
+
+impl Drone {
+    /// Enables or disables the events of the given kind, see
+    /// [`DroneControl::SetEventEnabled`](crate::DroneControl::SetEventEnabled).
+    pub(super) fn set_event_enabled(&mut self, kind: EventKind, enabled: bool) {
+        if enabled {
+            drone_status!(self, "{:?} events enabled", kind);
+            self.settings.disabled_events.remove(&kind);
+        } else if kind == EventKind::ControllerShortcut {
+            drone_error!(self, "Controller shortcut events cannot be disabled");
+        } else {
+            drone_status!(self, "{:?} events disabled", kind);
+            self.settings.disabled_events.insert(kind);
+        }
+    }
+
+    /// Returns `true` if the event kind is enabled and the event fits the configured rate limit.
+    ///
+    /// Events suppressed by the rate limit are counted in
+    /// [`DroneReport::events_suppressed`](super::DroneReport::events_suppressed).
+    pub(super) fn event_allowed(&mut self, event: &DroneEvent) -> bool {
+        let kind = EventKind::from(event);
+        if self.settings.disabled_events.contains(&kind) {
+            return false;
+        }
+        let Some(rate) = self.settings.event_rate_limit else {
+            return true;
+        };
+        if is_high_priority(event) || self.event_bucket.try_take(rate) {
+            return true;
+        }
+        self.stats.events_suppressed += 1;
+        false
+    }
+}
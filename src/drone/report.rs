@@ -0,0 +1,237 @@
+//! Packet counters and end-of-run diagnostics.
+//! Aggregates the drone's counters and state into a single printable report.
+
+use super::Drone;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use wg_2024::{
+    network::NodeId,
+    packet::{NackType, PacketType},
+};
+
+/// Column names of the lines produced by [`Drone::metrics_csv`].
+pub const METRICS_CSV_HEADER: &str = "id,timestamp_ms,fragments,acks,nacks,flood_requests,\
+    flood_responses,dropped,would_drop,nacks_error_in_routing,nacks_destination_is_drone,nacks_dropped,\
+    nacks_unexpected_recipient,floods_seen,floods_deduplicated,events_suppressed,pdr,effective_pdr,neighbors,throughput_pps,crashing";
+
+/// Reason why a drone left its event loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The drone received a `Crash` command and drained its queue.
+    Crashed,
+    /// The maximum lifetime set with [`Drone::with_max_lifetime`] elapsed.
+    LifetimeExpired,
+}
+
+/// Number of packets of each type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PacketCounts {
+    pub fragments: usize,
+    pub acks: usize,
+    pub nacks: usize,
+    pub flood_requests: usize,
+    pub flood_responses: usize,
+}
+
+impl PacketCounts {
+    /// Increments the counter matching the packet type.
+    pub(super) fn record(&mut self, pack_type: &PacketType) {
+        match pack_type {
+            PacketType::MsgFragment(_) => self.fragments += 1,
+            PacketType::Ack(_) => self.acks += 1,
+            PacketType::Nack(_) => self.nacks += 1,
+            PacketType::FloodRequest(_) => self.flood_requests += 1,
+            PacketType::FloodResponse(_) => self.flood_responses += 1,
+        }
+    }
+
+    /// Returns the total number of packets across all types.
+    pub fn total(&self) -> usize {
+        self.fragments + self.acks + self.nacks + self.flood_requests + self.flood_responses
+    }
+}
+
+/// Number of NACKs of each type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NackCounts {
+    pub error_in_routing: usize,
+    pub destination_is_drone: usize,
+    pub dropped: usize,
+    pub unexpected_recipient: usize,
+}
+
+impl NackCounts {
+    /// Increments the counter matching the NACK type.
+    pub(super) fn record(&mut self, nack_type: &NackType) {
+        match nack_type {
+            NackType::ErrorInRouting(_) => self.error_in_routing += 1,
+            NackType::DestinationIsDrone => self.destination_is_drone += 1,
+            NackType::Dropped => self.dropped += 1,
+            NackType::UnexpectedRecipient(_) => self.unexpected_recipient += 1,
+        }
+    }
+}
+
+/// Counters updated by the drone while it handles packets.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Stats {
+    pub(super) received: PacketCounts,
+    pub(super) dropped: usize,
+    /// Fragments that would have been dropped in measure-only mode.
+    pub(super) would_drop: usize,
+    pub(super) nacks_sent: NackCounts,
+    pub(super) floods_seen: usize,
+    pub(super) floods_deduplicated: usize,
+    pub(super) events_suppressed: usize,
+}
+
+/// Summary of a drone's activity, e.g. for a post-mortem at the end of a simulation.
+///
+/// The [`fmt::Display`] implementation renders a human-readable multi-line summary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DroneReport {
+    pub id: NodeId,
+    pub received: PacketCounts,
+    pub dropped: usize,
+    /// Fragments the PDR would have dropped while measure-only mode was on, see
+    /// [`DroneControl::SetMeasureOnly`](crate::DroneControl::SetMeasureOnly).
+    pub would_drop: usize,
+    pub nacks_sent: NackCounts,
+    pub floods_seen: usize,
+    pub floods_deduplicated: usize,
+    /// Events dropped by the event rate limit, see
+    /// [`DroneControl::SetEventRateLimit`](crate::DroneControl::SetEventRateLimit).
+    pub events_suppressed: usize,
+    pub neighbors: Vec<NodeId>,
+    pub pdr: f32,
+    /// Drop rate actually applied to fragments, see [`Drone::effective_pdr`].
+    pub effective_pdr: f32,
+    pub exit_reason: Option<ExitReason>,
+}
+
+impl fmt::Display for DroneReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Drone {} report", self.id)?;
+        writeln!(
+            f,
+            "  received: {} (fragments {}, acks {}, nacks {}, flood requests {}, flood responses {})",
+            self.received.total(),
+            self.received.fragments,
+            self.received.acks,
+            self.received.nacks,
+            self.received.flood_requests,
+            self.received.flood_responses
+        )?;
+        writeln!(f, "  dropped: {}", self.dropped)?;
+        writeln!(f, "  would drop (measure-only): {}", self.would_drop)?;
+        writeln!(
+            f,
+            "  nacks sent: error in routing {}, destination is drone {}, dropped {}, unexpected recipient {}",
+            self.nacks_sent.error_in_routing,
+            self.nacks_sent.destination_is_drone,
+            self.nacks_sent.dropped,
+            self.nacks_sent.unexpected_recipient
+        )?;
+        writeln!(
+            f,
+            "  floods: seen {}, deduplicated {}",
+            self.floods_seen, self.floods_deduplicated
+        )?;
+        writeln!(f, "  events suppressed: {}", self.events_suppressed)?;
+        writeln!(f, "  neighbors: {:?}", self.neighbors)?;
+        writeln!(f, "  pdr: {} (effective {})", self.pdr, self.effective_pdr)?;
+        match self.exit_reason {
+            Some(reason) => write!(f, "  exit reason: {:?}", reason),
+            None => write!(f, "  exit reason: still running"),
+        }
+    }
+}
+
+/// Snapshot of the figures shown on a controller status panel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DroneStatus {
+    /// Time since the event loop started, or `None` if `run` was never called.
+    pub uptime: Option<Duration>,
+    pub packets_received: usize,
+    pub throughput_pps: f64,
+    pub pdr: f32,
+    /// Drop rate actually applied to fragments, see [`Drone::effective_pdr`].
+    pub effective_pdr: f32,
+    pub neighbor_count: usize,
+    pub crashing: bool,
+    pub paused: bool,
+}
+
+impl Drone {
+    /// Returns why the drone left its event loop, or `None` while it is still running.
+    pub fn exit_reason(&self) -> Option<ExitReason> {
+        self.exit_reason
+    }
+
+    /// Returns the drone's uptime, packet figures, PDR, neighbor count and crashing state at once.
+    pub fn status(&self) -> DroneStatus {
+        DroneStatus {
+            uptime: self.started_at.map(|started_at| started_at.elapsed()),
+            packets_received: self.stats.received.total(),
+            throughput_pps: self.throughput_pps(),
+            pdr: self.pdr,
+            effective_pdr: self.effective_pdr(),
+            neighbor_count: self.packet_send.len(),
+            crashing: self.is_crashing(),
+            paused: self.paused,
+        }
+    }
+
+    /// Returns the drone's counters as one CSV line (without trailing newline), for appending to a
+    /// metrics file shared by all drones. Columns are listed in [`METRICS_CSV_HEADER`]; the
+    /// timestamp is in milliseconds since the Unix epoch.
+    pub fn metrics_csv(&self) -> String {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        let received = &self.stats.received;
+        let nacks_sent = &self.stats.nacks_sent;
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.3},{}",
+            self.id,
+            timestamp_ms,
+            received.fragments,
+            received.acks,
+            received.nacks,
+            received.flood_requests,
+            received.flood_responses,
+            self.stats.dropped,
+            self.stats.would_drop,
+            nacks_sent.error_in_routing,
+            nacks_sent.destination_is_drone,
+            nacks_sent.dropped,
+            nacks_sent.unexpected_recipient,
+            self.stats.floods_seen,
+            self.stats.floods_deduplicated,
+            self.stats.events_suppressed,
+            self.pdr,
+            self.effective_pdr(),
+            self.packet_send.len(),
+            self.throughput_pps(),
+            self.crashing_behavior
+        )
+    }
+
+    /// Returns a summary of the drone's counters and state.
+    pub fn report(&self) -> DroneReport {
+        DroneReport {
+            id: self.id,
+            received: self.stats.received,
+            dropped: self.stats.dropped,
+            would_drop: self.stats.would_drop,
+            nacks_sent: self.stats.nacks_sent,
+            floods_seen: self.stats.floods_seen,
+            floods_deduplicated: self.stats.floods_deduplicated,
+            events_suppressed: self.stats.events_suppressed,
+            neighbors: self.neighbors(),
+            pdr: self.pdr,
+            effective_pdr: self.effective_pdr(),
+            exit_reason: self.exit_reason,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Pre-flight validation of source routes.
+//! Lets a controller check planned routes against the drone's runtime checks before spawning it.
+
+use super::{Drone, MAX_ROUTE_LENGTH};
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::RangeInclusive;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+
+/// Reason a route would not be forwarded by the drone at its current hop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteError {
+    /// The route has more hops than the drone accepts; carries the hop count.
+    TooLong(usize),
+    /// `hop_index` is 0, which points at the source rather than at a drone.
+    HopIndexZero,
+    /// `hop_index` does not point into the route.
+    HopIndexOutOfBounds { hop_index: usize, len: usize },
+    /// The route visits the same node twice in a row.
+    SelfLoop(NodeId),
+    /// The route ends at the drone at `hop_index`, which fragments must never do.
+    EndsAtDrone(NodeId),
+    /// The next hop is not a neighbor of the drone at `hop_index`.
+    UnreachableNextHop(NodeId),
+    /// The route's destination is in the drone ID range, which fragments must never target.
+    DestinationIsDrone(NodeId),
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::TooLong(len) => {
+                write!(f, "route has {} hops (max {})", len, MAX_ROUTE_LENGTH)
+            }
+            RouteError::HopIndexZero => write!(f, "hop_index 0 points at the source"),
+            RouteError::HopIndexOutOfBounds { hop_index, len } => {
+                write!(
+                    f,
+                    "hop_index {} is outside a route of {} hops",
+                    hop_index, len
+                )
+            }
+            RouteError::SelfLoop(node_id) => write!(f, "route repeats node {}", node_id),
+            RouteError::EndsAtDrone(node_id) => write!(f, "route ends at drone {}", node_id),
+            RouteError::UnreachableNextHop(node_id) => {
+                write!(f, "next hop {} is not a neighbor", node_id)
+            }
+            RouteError::DestinationIsDrone(node_id) => {
+                write!(f, "destination {} is a drone", node_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+/// Returns `true` if the route has more hops than the drone accepts.
+pub(super) fn route_too_long(hops: &[NodeId]) -> bool {
+    hops.len() > MAX_ROUTE_LENGTH
+}
+
+/// Returns the first node the route visits twice in a row, if any.
+pub(super) fn repeated_hop(hops: &[NodeId]) -> Option<NodeId> {
+    hops.windows(2)
+        .find(|pair| pair[0] == pair[1])
+        .map(|pair| pair[0])
+}
+
+/// Returns the hop following `hop_index`, or `None` if the route ends there.
+pub(super) fn next_hop(routing_header: &SourceRoutingHeader) -> Option<NodeId> {
+    routing_header
+        .hop_index
+        .checked_add(1)
+        .and_then(|next_index| routing_header.hops.get(next_index))
+        .copied()
+}
+
+/// Returns the route's destination if it falls in the drone ID range.
+pub(super) fn destination_in_drone_range(
+    hops: &[NodeId],
+    drone_id_range: &RangeInclusive<NodeId>,
+) -> Option<NodeId> {
+    hops.last()
+        .copied()
+        .filter(|destination| drone_id_range.contains(destination))
+}
+
+impl Drone {
+    /// Checks a route as the drone at `hop_index` would when receiving a fragment along it, given
+    /// that drone's neighbors and, optionally, the range of drone IDs.
+    ///
+    /// This is a pure pre-flight check using the same predicates as the drone at runtime, in the
+    /// same order: length, `hop_index` 0, bounds, consecutive repeated hops, end of the route,
+    /// reachability of the next hop and, when a range is given, a destination in the drone ID
+    /// range (see [`DroneControl::SetDroneIdRange`](crate::DroneControl::SetDroneIdRange)).
+    pub fn validate_route(
+        routing_header: &SourceRoutingHeader,
+        neighbors: &HashSet<NodeId>,
+        drone_id_range: Option<&RangeInclusive<NodeId>>,
+    ) -> Result<(), RouteError> {
+        let hops = &routing_header.hops;
+        if route_too_long(hops) {
+            return Err(RouteError::TooLong(hops.len()));
+        }
+        if routing_header.hop_index == 0 {
+            return Err(RouteError::HopIndexZero);
+        }
+        let Some(&current) = hops.get(routing_header.hop_index) else {
+            return Err(RouteError::HopIndexOutOfBounds {
+                hop_index: routing_header.hop_index,
+                len: hops.len(),
+            });
+        };
+        if let Some(repeated) = repeated_hop(hops) {
+            return Err(RouteError::SelfLoop(repeated));
+        }
+        let Some(next_hop) = next_hop(routing_header) else {
+            return Err(RouteError::EndsAtDrone(current));
+        };
+        if !neighbors.contains(&next_hop) {
+            return Err(RouteError::UnreachableNextHop(next_hop));
+        }
+        if let Some(destination) =
+            drone_id_range.and_then(|range| destination_in_drone_range(hops, range))
+        {
+            return Err(RouteError::DestinationIsDrone(destination));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+//! Rolling throughput measurement.
+//! Counts processed packets in one-second buckets covering a short sliding window.
+
+use super::Drone;
+use std::time::Instant;
+
+/// Length of the throughput window, in seconds.
+const WINDOW_SECS: u64 = 5;
+
+/// Ring of per-second packet counts, indexed by seconds since the meter was created.
+#[derive(Debug, Clone)]
+pub(super) struct Throughput {
+    start: Instant,
+    buckets: [(u64, usize); WINDOW_SECS as usize],
+}
+
+impl Throughput {
+    pub(super) fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            buckets: [(0, 0); WINDOW_SECS as usize],
+        }
+    }
+
+    /// Counts one packet in the bucket of the current second.
+    pub(super) fn record(&mut self) {
+        let second = self.start.elapsed().as_secs();
+        let bucket = &mut self.buckets[(second % WINDOW_SECS) as usize];
+        if bucket.0 != second {
+            *bucket = (second, 0);
+        }
+        bucket.1 += 1;
+    }
+
+    /// Returns the packets per second over the buckets still inside the window.
+    fn packets_per_second(&self) -> f64 {
+        let elapsed = self.start.elapsed();
+        let second = elapsed.as_secs();
+        let oldest = (second + 1).saturating_sub(WINDOW_SECS);
+
+        let span = elapsed.as_secs_f64() - oldest as f64;
+        if span <= 0.0 {
+            return 0.0;
+        }
+        let count: usize = self
+            .buckets
+            .iter()
+            .filter(|(bucket_second, _)| (oldest..=second).contains(bucket_second))
+            .map(|(_, count)| count)
+            .sum();
+        count as f64 / span
+    }
+}
+
+impl Drone {
+    /// Returns the number of packets processed per second over roughly the last 5 seconds.
+    ///
+    /// The value is approximate: packets are counted in one-second buckets, so the window
+    /// covers between 4 and 5 seconds depending on when it is sampled.
+    pub fn throughput_pps(&self) -> f64 {
+        self.throughput.packets_per_second()
+    }
+}
@@ -11,8 +11,18 @@
 //! dr_ones::enable_logging();
 //! ```
 
+mod control;
 mod drone;
-pub use drone::Drone;
+pub use control::{
+    CrashDrainMode, CrashDrainOrder, DestinationIsDronePolicy, DroneControl, DroneNotification,
+    DropReason, DuplicateHopPolicy, EventKind, FloodFanOutPolicy, FloodInitiatorCheck,
+    FloodSessionStrategy, FragmentTotalCheck, HopIndexZeroPolicy, PacketKind, PdrPreset,
+    SessionRetention, ShortcutFailurePolicy,
+};
+pub use drone::{
+    CorrelatedEvent, Drone, DroneConfig, DroneReport, DroneStatus, ExitReason, NackCounts,
+    PacketCounts, RouteError, METRICS_CSV_HEADER,
+};
 
 // Re-export logging control functions
 pub use network_node::{disable_logging, enable_logging, redirect_logs_to_file};
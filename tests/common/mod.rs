@@ -0,0 +1,108 @@
+//! Helpers shared by the integration tests.
+//! Using 1-10 for clients, 11-20 for drones and 21-30 for servers
+#![allow(dead_code)]
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use dr_ones::Drone;
+use std::collections::HashMap;
+use std::thread;
+use wg_2024::{
+    controller::{DroneCommand, DroneEvent},
+    drone::Drone as _,
+    network::NodeId,
+    packet::Packet,
+};
+
+/// ID of the client at the start of a chain.
+pub const CLIENT_ID: NodeId = 1;
+/// ID of the first drone of a chain; the following drones get consecutive IDs.
+pub const FIRST_DRONE_ID: NodeId = 11;
+/// ID of the server at the end of a chain.
+pub const SERVER_ID: NodeId = 21;
+
+/// A client, a line of drones and a server, each connected to the next.
+///
+/// The drones are not running: tests can drive them synchronously, or start them with
+/// [`Chain::spawn`].
+pub struct Chain {
+    pub drones: Vec<Drone>,
+    /// Channels delivering packets to each drone, e.g. to inject packets from the client side.
+    pub drone_send: Vec<Sender<Packet>>,
+    /// Inbound channels of each drone, e.g. to inspect what a neighbor sent it.
+    pub drone_recv: Vec<Receiver<Packet>>,
+    /// Command channels of each drone.
+    pub commands: Vec<Sender<DroneCommand>>,
+    /// Events emitted by each drone.
+    pub events: Vec<Receiver<DroneEvent>>,
+    pub client_recv: Receiver<Packet>,
+    pub server_recv: Receiver<Packet>,
+}
+
+impl Chain {
+    /// Builds a chain with one drone per PDR, in order from the client to the server.
+    pub fn new(pdrs: &[f32]) -> Self {
+        let (client_send, client_recv) = unbounded();
+        let (server_send, server_recv) = unbounded();
+        let (drone_send, drone_recv): (Vec<Sender<Packet>>, Vec<Receiver<Packet>>) =
+            pdrs.iter().map(|_| unbounded()).unzip();
+
+        let mut chain = Chain {
+            drones: Vec::new(),
+            drone_send,
+            drone_recv,
+            commands: Vec::new(),
+            events: Vec::new(),
+            client_recv,
+            server_recv,
+        };
+
+        for (index, pdr) in pdrs.iter().enumerate() {
+            let previous = match index {
+                0 => (CLIENT_ID, client_send.clone()),
+                _ => (
+                    Self::drone_id(index - 1),
+                    chain.drone_send[index - 1].clone(),
+                ),
+            };
+            let next = match chain.drone_send.get(index + 1) {
+                Some(next_send) => (Self::drone_id(index + 1), next_send.clone()),
+                None => (SERVER_ID, server_send.clone()),
+            };
+
+            let (event_send, event_recv) = unbounded();
+            let (command_send, command_recv) = unbounded();
+            chain.drones.push(Drone::new(
+                Self::drone_id(index),
+                event_send,
+                command_recv,
+                chain.drone_recv[index].clone(),
+                HashMap::from([previous, next]),
+                *pdr,
+            ));
+            chain.commands.push(command_send);
+            chain.events.push(event_recv);
+        }
+        chain
+    }
+
+    /// Returns the ID of the drone at `index` in the chain.
+    pub fn drone_id(index: usize) -> NodeId {
+        FIRST_DRONE_ID + index as NodeId
+    }
+
+    /// Returns the route from the client through every drone to the server.
+    pub fn route(&self) -> Vec<NodeId> {
+        let drones = (0..self.drones.len()).map(Self::drone_id);
+        std::iter::once(CLIENT_ID)
+            .chain(drones)
+            .chain(std::iter::once(SERVER_ID))
+            .collect()
+    }
+
+    /// Runs every drone on its own thread.
+    pub fn spawn(&mut self) {
+        for mut drone in self.drones.drain(..) {
+            thread::spawn(move || drone.run());
+        }
+    }
+}
@@ -1,3 +1,6 @@
+mod common;
+
+use common::Chain;
 use crossbeam_channel::unbounded;
 use std::collections::HashMap;
 use std::thread;
@@ -108,3 +111,41 @@ fn generic_drone_crash<T: Drone + Send + 'static>() {
 fn test_drone_crash() {
     generic_drone_crash::<dr_ones::Drone>();
 }
+
+/// Tests the cross-node crash semantics: a forward towards a neighbor that is draining its
+/// queue succeeds, and the fragment only comes back as a NACK once the neighbor handles it.
+#[test]
+fn test_forward_to_crashing_neighbor() {
+    let mut chain = Chain::new(&[0.0, 0.0]);
+    let [drone, drone2] = &mut chain.drones[..] else {
+        unreachable!()
+    };
+
+    // The forward succeeds: the fragment is queued at drone 12
+    drone.process_packet_once(create_sample_packet());
+    assert_eq!(drone2.inbound_queue_depth(), 1);
+    assert!(chain.client_recv.try_recv().is_err());
+
+    // Drone 12 crashes and NACKs the queued fragment back to drone 11
+    drone2.apply_command(DroneCommand::Crash);
+    assert_eq!(drone2.inbound_queue_depth(), 0);
+    let nack = chain.drone_recv[0]
+        .try_recv()
+        .expect("NACK expected at drone 11");
+
+    // Drone 11 relays the NACK to the client
+    drone.process_packet_once(nack);
+    let nack = chain
+        .client_recv
+        .try_recv()
+        .expect("NACK expected at the client");
+    assert_eq!(
+        nack.pack_type,
+        PacketType::Nack(Nack {
+            nack_type: NackType::ErrorInRouting(12),
+            fragment_index: 1,
+        })
+    );
+    assert_eq!(nack.routing_header.hops, vec![12, 11, 1]);
+    assert_eq!(nack.session_id, 1);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{Chain, CLIENT_ID};
+use crossbeam_channel::unbounded;
+use dr_ones::DroneControl;
+use std::thread;
+use std::time::Duration;
+use wg_2024::{
+    controller::DroneCommand,
+    drone::Drone as _,
+    network::SourceRoutingHeader,
+    packet::{FloodRequest, NodeType, Packet, PacketType},
+};
+
+/// Tests that a flood answered at max depth counts as seen, so a later request for it is answered
+/// as a duplicate instead of being forwarded once the cap is lifted.
+#[test]
+fn max_depth_flood_seen() {
+    let mut chain = Chain::new(&[0.0, 0.0]);
+    let (control_send, control_recv) = unbounded();
+    let mut drone = chain.drones.remove(0).with_control_channel(control_recv);
+    let flood = Packet {
+        pack_type: PacketType::FloodRequest(FloodRequest {
+            flood_id: 1,
+            initiator_id: CLIENT_ID,
+            path_trace: vec![(CLIENT_ID, NodeType::Client)],
+        }),
+        routing_header: SourceRoutingHeader {
+            hop_index: 0,
+            hops: vec![],
+        },
+        session_id: 0,
+    };
+
+    // Only the first drone runs, so requests it forwards stay queued for the second one
+    control_send
+        .send(DroneControl::SetMaxFloodDepth(Some(2)))
+        .unwrap();
+    chain.drone_send[0].send(flood.clone()).unwrap();
+    let handle = thread::spawn(move || drone.run());
+
+    let response = chain
+        .client_recv
+        .recv_timeout(Duration::from_secs(1))
+        .expect("Flood response expected at max depth");
+    assert!(matches!(response.pack_type, PacketType::FloodResponse(_)));
+
+    control_send
+        .send(DroneControl::SetMaxFloodDepth(None))
+        .unwrap();
+    chain.drone_send[0].send(flood).unwrap();
+    let response = chain
+        .client_recv
+        .recv_timeout(Duration::from_secs(1))
+        .expect("Flood response expected for the duplicate request");
+    assert!(matches!(response.pack_type, PacketType::FloodResponse(_)));
+
+    chain.commands[0].send(DroneCommand::Crash).unwrap();
+    handle.join().unwrap();
+    assert!(chain.drone_recv[1].try_recv().is_err());
+}
@@ -1,4 +1,12 @@
-use wg_2024::tests;
+mod common;
+
+use common::Chain;
+use std::time::Duration;
+use wg_2024::{
+    network::SourceRoutingHeader,
+    packet::{Fragment, Packet, PacketType},
+    tests,
+};
 
 #[test]
 fn fragment_forward() {
@@ -19,3 +27,31 @@ fn chain_fragment_drop() {
 fn chain_fragment_ack() {
     tests::generic_chain_fragment_ack::<dr_ones::Drone>();
 }
+
+/// Tests that a fragment crosses a chain of running drones to the server.
+#[test]
+fn chain_fragment_forward() {
+    let mut chain = Chain::new(&[0.0, 0.0, 0.0]);
+    let fragment = Packet {
+        pack_type: PacketType::MsgFragment(Fragment {
+            fragment_index: 0,
+            total_n_fragments: 1,
+            length: 128,
+            data: [1; 128],
+        }),
+        routing_header: SourceRoutingHeader {
+            hop_index: 1,
+            hops: chain.route(),
+        },
+        session_id: 1,
+    };
+    chain.spawn();
+
+    chain.drone_send[0].send(fragment).unwrap();
+    let received = chain
+        .server_recv
+        .recv_timeout(Duration::from_secs(1))
+        .expect("Fragment expected at the server");
+    assert_eq!(received.routing_header.hop_index, 4);
+    assert_eq!(received.routing_header.hops, vec![1, 11, 12, 13, 21]);
+}